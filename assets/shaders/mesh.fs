@@ -2,8 +2,28 @@
 
 out vec4 Target0;
 in vec4 v_Color;
+in vec3 v_Normal;
+
+uniform Transform {
+    mat4 u_Model;
+    mat4 u_View;
+    mat4 u_Proj;
+    vec4 u_WireframeColor;
+    vec3 u_Ambient;
+};
+
+// Fixed sun direction, only used to give the ambient tint some shape
+const vec3 c_Sun = vec3(0.3, 0.9, 0.3);
+const float c_Diffuse = 0.35;
 
 void main() {
-    Target0 = v_Color;
+    // Meshes without normals (e.g. the trail) only receive the ambient term
+    float len = length(v_Normal);
+    float diffuse = len > 0.0 ? max(dot(v_Normal / len, normalize(c_Sun)), 0.0) : 0.0;
+
+    // Ambient plus diffuse is clamped so bright presets never blow out the
+    // vertex colors to white
+    vec3 light = min(u_Ambient + diffuse * c_Diffuse, vec3(1.0));
+    Target0 = vec4(v_Color.rgb * light, v_Color.a);
 }
 
@@ -1,17 +1,22 @@
 #version 150 core
 
 in vec3 a_Pos;
+in vec3 a_Normal;
 in vec4 a_Color;
 out vec4 v_Color;
+out vec3 v_Normal;
 
 uniform Transform {
     mat4 u_Model;
     mat4 u_View;
     mat4 u_Proj;
+    vec4 u_WireframeColor;
+    vec3 u_Ambient;
 };
 
 void main() {
-    v_Color = a_Color;
+    v_Color = mix(a_Color, vec4(u_WireframeColor.rgb, a_Color.a), u_WireframeColor.a);
+    v_Normal = mat3(u_Model) * a_Normal;
     gl_ClipDistance[0] = 1.0;
     gl_Position = u_Proj * u_View * (u_Model * vec4(a_Pos, 1.0));
 }
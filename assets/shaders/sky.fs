@@ -0,0 +1,14 @@
+#version 150 core
+
+out vec4 Target0;
+in float v_Height;
+
+uniform Gradient {
+    vec4 u_Top;
+    vec4 u_Bottom;
+};
+
+void main() {
+    Target0 = mix(u_Bottom, u_Top, v_Height);
+}
+
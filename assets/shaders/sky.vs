@@ -0,0 +1,10 @@
+#version 150 core
+
+in vec2 a_Pos;
+out float v_Height;
+
+void main() {
+    v_Height = a_Pos.y * 0.5 + 0.5;
+    gl_Position = vec4(a_Pos, 0.0, 1.0);
+}
+
@@ -11,6 +11,10 @@
 use cgmath::{Vector3, InnerSpace};
 
 
+// Internal Dependencies ------------------------------------------------------
+use ::core::math::lerp;
+
+
 // 3D Bezier Implementation ---------------------------------------------------
 #[derive(Debug)]
 pub struct Bezier {
@@ -32,20 +36,26 @@ impl Bezier {
         let (r1, r2) = (self.points.1.roll,  self.points.2.roll);
         let (w1, w2) = (self.points.1.width, self.points.2.width);
 
+        // Rotation minimizing frame, the binormal of the previous row is
+        // carried along the curve via double reflection instead of being
+        // recomputed per row, which avoids sudden flips on ramps and 3D curves
+        let mut frame: Option<(Vector3<f32>, Vector3<f32>, Vector3<f32>)> = None;
+
         let mut t = 0.0f32;
         loop {
 
-            let py = self.point(t.min(1.0), true);
-            let p = self.point(t.min(1.0), false);
-            let p2 = self.point((t + 0.002), false);
-
-            let deriv = self.derivative(t);
+            let py = self.point(t.min(1.0));
+            let deriv = self.derivative(t.min(1.0));
             let len = deriv.magnitude();
-            let ta = (p2 - p).normalize();
-            let mut b = ta.cross(p2 + p).normalize();
-            b.y = b.y.abs();
+            let ta = deriv.normalize();
+
+            let b = match frame {
+                Some((prev_pos, prev_ta, prev_b)) => transport(prev_pos, prev_ta, prev_b, py, ta),
+                None => initial_binormal(ta)
+            };
 
             let n = b.cross(ta).normalize();
+            frame = Some((py, ta, b));
             segments.push(Row {
                 pos: py,
                 binormal: b,
@@ -66,17 +76,13 @@ impl Bezier {
 
     }
 
-    fn point(&self, t: f32, with_y: bool) -> Vector3<f32> {
+    fn point(&self, t: f32) -> Vector3<f32> {
 		let dt = 1.0 - t;
 		let dt2 = dt * dt;
 		let t2 = t * t;
         let ref p = self.points;
 
-		let mut v = (p.0.pos * dt2 * dt) + (p.1.pos * 3.0 * dt2 * t) + (p.2.pos * 3.0 * dt * t2) + (p.3.pos * t2 * t);
-        if !with_y {
-            v.y = 0.0;
-        }
-        v
+		(p.0.pos * dt2 * dt) + (p.1.pos * 3.0 * dt2 * t) + (p.2.pos * 3.0 * dt * t2) + (p.3.pos * t2 * t)
     }
 
     fn derivative(&self, t: f32) -> Vector3<f32> {
@@ -91,8 +97,54 @@ impl Bezier {
 
 
 // Helpers --------------------------------------------------------------------
-fn lerp(a: f32, b: f32, t: f32) -> f32 {
-    a * (1.0 - t) + b * t
+fn initial_binormal(tangent: Vector3<f32>) -> Vector3<f32> {
+
+    // World up made orthogonal to the tangent, a vertical start falls back
+    // to the X axis
+    let up = Vector3::new(0.0, 1.0, 0.0);
+    let b = up - tangent * up.dot(tangent);
+    if b.magnitude2() > 0.000001 {
+        b.normalize()
+
+    } else {
+        let x = Vector3::new(1.0, 0.0, 0.0);
+        (x - tangent * x.dot(tangent)).normalize()
+    }
+
+}
+
+// Double reflection method from "Computation of Rotation Minimizing Frames"
+// by Wang et al.
+fn transport(
+    prev_pos: Vector3<f32>,
+    prev_tangent: Vector3<f32>,
+    prev_binormal: Vector3<f32>,
+    pos: Vector3<f32>,
+    tangent: Vector3<f32>
+
+) -> Vector3<f32> {
+
+    let mut b = prev_binormal;
+    let mut ta = prev_tangent;
+
+    // Reflect across the plane bisecting both positions
+    let v1 = pos - prev_pos;
+    let c1 = v1.magnitude2();
+    if c1 > 0.000001 {
+        b = b - v1 * (2.0 / c1 * v1.dot(b));
+        ta = ta - v1 * (2.0 / c1 * v1.dot(ta));
+    }
+
+    // Reflect the result onto the new tangent
+    let v2 = tangent - ta;
+    let c2 = v2.magnitude2();
+    if c2 > 0.000001 {
+        b = b - v2 * (2.0 / c2 * v2.dot(b));
+    }
+
+    // Remove accumulated drift
+    (b - tangent * b.dot(tangent)).normalize()
+
 }
 
 #[derive(Debug, Clone)]
@@ -138,3 +190,70 @@ pub struct Row {
     pub roll: f32
 }
 
+impl Row {
+
+    // Surface normal of the rolled cross section
+    pub fn up(&self) -> Vector3<f32> {
+        let roll = ::std::f32::consts::PI / 180.0 * self.roll;
+        self.binormal * roll.cos() + self.normal * roll.sin()
+    }
+
+}
+
+
+
+// Tests ----------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+
+    use cgmath::InnerSpace;
+    use super::{Bezier, Point, Row};
+
+    // Dot product of the frames of neighbouring rows, 1.0 for parallel axes
+    fn min_continuity(rows: &[Row]) -> (f32, f32) {
+        rows.windows(2).fold((1.0f32, 1.0f32), |(b, n), r| {
+            (b.min(r[0].binormal.dot(r[1].binormal)), n.min(r[0].normal.dot(r[1].normal)))
+        })
+    }
+
+    fn check_frames(bezier: Bezier) {
+
+        let rows = bezier.generate_segments(10.0);
+        assert!(rows.len() > 50);
+
+        for (i, r) in rows.iter().enumerate() {
+            assert!((r.binormal.magnitude() - 1.0).abs() < 0.001, "row {} binormal not normalized", i);
+            assert!((r.normal.magnitude() - 1.0).abs() < 0.001, "row {} normal not normalized", i);
+            assert!(r.binormal.dot(r.normal).abs() < 0.001, "row {} frame not orthogonal", i);
+        }
+
+        // Rows are at most a few degrees apart, a flip shows up as a dot
+        // product close to -1
+        let (b, n) = min_continuity(&rows);
+        assert!(b > 0.99, "binormal jumps between rows ({})", b);
+        assert!(n > 0.99, "normal jumps between rows ({})", n);
+
+    }
+
+    #[test]
+    fn test_frame_continuity_descending_curve() {
+        check_frames(Bezier::new(
+            Point::new(0.0, 500.0, 0.0, 100.0, 0.0),
+            Point::new(400.0, 500.0, 0.0, 100.0, 0.0),
+            Point::new(500.0, 0.0, 100.0, 100.0, 0.0),
+            Point::new(500.0, -300.0, 500.0, 100.0, 0.0)
+        ));
+    }
+
+    #[test]
+    fn test_frame_continuity_steep_drop() {
+        check_frames(Bezier::new(
+            Point::new(0.0, 0.0, 0.0, 100.0, 0.0),
+            Point::new(300.0, 0.0, 0.0, 100.0, 0.0),
+            Point::new(300.0, -300.0, 0.0, 100.0, 0.0),
+            Point::new(0.0, -600.0, 200.0, 100.0, 0.0)
+        ));
+    }
+
+}
+
@@ -0,0 +1,79 @@
+// Copyright (c) 2017 Ivo Wetzel
+
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+// Key Binding Registry -------------------------------------------------------
+pub struct Binding {
+    pub context: &'static str,
+    pub keys: &'static str,
+    pub description: &'static str
+}
+
+// Keep in sync with the key handling in Game, Camera, Course and Segment
+pub const BINDINGS: &[Binding] = &[
+    Binding { context: "Game", keys: "F1", description: "Toggle this help" },
+    Binding { context: "Game", keys: "Tab", description: "Toggle edit / play mode" },
+    Binding { context: "Game", keys: "B", description: "Toggle wireframe (Shift cycle wireframe style)" },
+    Binding { context: "Game", keys: "R", description: "Reload shaders and reset glider" },
+    Binding { context: "Game", keys: "H", description: "Toggle grid and debug overlays" },
+    Binding { context: "Game", keys: "Shift H", description: "Toggle collision geometry and spatial index overlay" },
+    Binding { context: "Game", keys: "P", description: "Print frame and render stats" },
+    Binding { context: "Game", keys: "Shift P", description: "Save a course thumbnail to thumbnail.png" },
+    Binding { context: "Game", keys: "F4", description: "Cycle time of day" },
+    Binding { context: "Game", keys: "F6", description: "Toggle split screen glider preview" },
+    Binding { context: "Game", keys: "5 6 7", description: "Time scale 0.25x / 1x / 2x" },
+    Binding { context: "Game", keys: "0", description: "Pause / resume glider" },
+    Binding { context: "Game", keys: "Alt + -", description: "Larger / smaller debug gizmos" },
+
+    Binding { context: "Editor", keys: "Z X", description: "Smaller / larger grid (Shift cells)" },
+    Binding { context: "Editor", keys: "F2", description: "Toggle row coordinate frames" },
+    Binding { context: "Editor", keys: "F3", description: "Toggle next segment preview" },
+    Binding { context: "Editor", keys: "F5", description: "Print active segment and course stats" },
+    Binding { context: "Editor", keys: "F7 F8 F9", description: "Toggle checkpoint / boost pad / finish tag" },
+    Binding { context: "Editor", keys: "F11", description: "Attach active end point to the closest segment" },
+    Binding { context: "Editor", keys: "F12", description: "Toggle gravity zone pulling onto the active segment (Shift updraft)" },
+    Binding { context: "Editor", keys: "Y", description: "Toggle snapping end points to the grid" },
+    Binding { context: "Editor", keys: "+ -", description: "Finer / coarser tessellation of all segments" },
+    Binding { context: "Editor", keys: "Click", description: "Select segment" },
+    Binding { context: "Editor", keys: "Del", description: "Delete segment, later ones close the gap" },
+    Binding { context: "Editor", keys: "Ctrl Z", description: "Restore deleted segment" },
+    Binding { context: "Editor", keys: "PgUp PgDn", description: "Move segment earlier / later in the course order" },
+    Binding { context: "Editor", keys: "Ctrl C V", description: "Copy segment / paste at course end" },
+    Binding { context: "Editor", keys: "Esc", description: "Type a segment number, Return selects" },
+    Binding { context: "Editor", keys: "Return", description: "Toggle orbit camera (drag, wheel)" },
+
+    Binding { context: "Camera", keys: "W S", description: "Pitch down / up" },
+    Binding { context: "Camera", keys: "A D", description: "Yaw left / right" },
+    Binding { context: "Camera", keys: "Q E", description: "Move up / down" },
+    Binding { context: "Camera", keys: "Space Back", description: "Move forward / backward" },
+    Binding { context: "Camera", keys: "C", description: "Reset camera (Shift frame the whole course)" },
+
+    Binding { context: "Segment", keys: "G", description: "Toggle active end point" },
+    Binding { context: "Segment", keys: "1 2 3 4", description: "Straight / 90 / 180 curve / looping" },
+    Binding { context: "Segment", keys: "U O", description: "Rotate -90 / +90" },
+    Binding { context: "Segment", keys: "I K J L", description: "Move end point by a grid cell (Shift both)" },
+    Binding { context: "Segment", keys: "Ctrl Alt", description: "Hold for 1/10 / 10x end point movement" },
+    Binding { context: "Segment", keys: "V", description: "Mirror across X (Shift Z, Alt reverse direction)" },
+    Binding { context: "Segment", keys: "F10", description: "Auto bank curve (Shift level)" },
+    Binding { context: "Segment", keys: "T", description: "Toggle world / local movement" },
+    Binding { context: "Segment", keys: "N M", description: "Shorter / longer straight (Shift tighter / wider curve)" },
+    Binding { context: "Segment", keys: "8 9", description: "Fewer / more columns" },
+    Binding { context: "Segment", keys: "F", description: "Cycle width profile" },
+
+    Binding { context: "Glider", keys: "W", description: "Accelerate" },
+    Binding { context: "Glider", keys: "S", description: "Reverse" },
+    Binding { context: "Glider", keys: "A D", description: "Turn left / right" },
+    Binding { context: "Glider", keys: "Y", description: "Hold to rewind" },
+    Binding { context: "Glider", keys: "F", description: "Detach / attach camera" },
+    Binding { context: "Glider", keys: "V", description: "Cycle camera distance presets" },
+    Binding { context: "Glider", keys: "N M", description: "Slower / faster surface alignment (Shift floatier / tighter hover)" },
+    Binding { context: "Glider", keys: "T", description: "Cycle turn model" },
+    Binding { context: "Glider", keys: "G", description: "Toggle corner / default contact rays" },
+    Binding { context: "Glider", keys: "K", description: "Toggle model animation" }
+];
+
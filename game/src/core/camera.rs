@@ -13,81 +13,223 @@ use std::ops::Mul;
 
 // External Dependencies ------------------------------------------------------
 use cgmath::{self, Rotation3};
-use cgmath::{Deg, Euler, Quaternion, Vector3, Matrix4};
-use renderer::{Keyboard, Key};
+use cgmath::{Deg, Rad, Euler, Quaternion, Point3, Vector2, Vector3, Vector4, Matrix3, Matrix4, InnerSpace, Matrix, SquareMatrix, EuclideanSpace};
+use renderer::{Keyboard, Key, Viewport};
+
+
+// Statics --------------------------------------------------------------------
+const ORBIT_MIN_DISTANCE: f32 = 50.0;
+const ORBIT_MAX_PITCH: f32 = 85.0;
 
 
 // 3D Camera Implementation ---------------------------------------------------
 pub struct Camera {
     fov: f32,
+    aspect_ratio: f32,
+    clip_range: (f32, f32),
+    scale: (f32, f32),
+    offset: (f32, f32),
     pub rotation: Quaternion<f32>,
     pub position: Matrix4<f32>,
-    projection: Matrix4<f32>
+    projection: Matrix4<f32>,
+    defaults: (f32, Quaternion<f32>, Matrix4<f32>),
+    bounds: Option<(Vector3<f32>, Vector3<f32>)>,
+    orbit_target: Vector3<f32>,
+    orbit_distance: f32,
+    orbit_yaw: f32,
+    orbit_pitch: f32
 }
 
 impl Camera {
 
     pub fn new(width: u32, height: u32, fov: f32) -> Self {
-        let aspect_ratio = width as f32 / height as f32;
+        let aspect_ratio = width as f32 / height.max(1) as f32;
+        let rotation = Quaternion::from(Euler {
+            x: Deg(35.0),
+            y: Deg(0.0),
+            z: Deg(0.0),
+        });
+        let position = Matrix4::from_translation(Vector3::new(-100.0, -300.0, -600.0));
         Self {
             fov: fov,
-            rotation: Quaternion::from(Euler {
-                x: Deg(35.0),
-                y: Deg(0.0),
-                z: Deg(0.0),
-            }),
-            position: Matrix4::from_translation(Vector3::new(-100.0, -300.0, -600.0)),
-            projection: cgmath::perspective(Deg(fov), aspect_ratio, 0.01, 15000.0)
+            aspect_ratio: aspect_ratio,
+            clip_range: (0.01, 15000.0),
+            scale: (1.0, 1.0),
+            offset: (0.0, 0.0),
+            rotation: rotation,
+            position: position,
+            projection: cgmath::perspective(Deg(fov), aspect_ratio, 0.01, 15000.0),
+            defaults: (fov, rotation, position),
+            bounds: None,
+            orbit_target: Vector3::new(0.0, 0.0, 0.0),
+            orbit_distance: 1000.0,
+            orbit_yaw: 0.0,
+            orbit_pitch: 30.0
         }
     }
 
+    pub fn reset(&mut self) {
+        let (fov, rotation, position) = self.defaults;
+        self.fov = fov;
+        self.rotation = rotation;
+        self.position = position;
+        self.update_projection();
+        self.clamp();
+    }
+
+    // Limits the world space camera position to the given (min, max) volume
+    pub fn set_bounds(&mut self, bounds: Option<(Vector3<f32>, Vector3<f32>)>) {
+        self.bounds = bounds;
+        self.clamp();
+    }
+
     pub fn update(&mut self, keyboard: &Keyboard) {
 
-        // Pitch down
-        if keyboard.is_pressed(Key::W) {
-            self.pitch(1.5);
+        // Reset to initial overview, Ctrl+C copies a segment and Shift+C
+        // frames the course instead
+        if keyboard.was_pressed(Key::C) && !keyboard.is_pressed(Key::LControl) && !keyboard.is_pressed(Key::LShift) {
+            self.reset();
         }
 
-        // Pitch up
-        if keyboard.is_pressed(Key::S) {
-            self.pitch(-1.5);
-        }
+        // Accumulate all held directions first, opposing keys cancel out
+        let axis = |positive: Key, negative: Key| {
+            (keyboard.is_pressed(positive) as i32 - keyboard.is_pressed(negative) as i32) as f32
+        };
+
+        // Pitch down / up and yaw left / right
+        let pitch = axis(Key::W, Key::S);
+        let yaw = axis(Key::D, Key::A);
 
-        // Yaw Left
-        if keyboard.is_pressed(Key::A) {
-            self.yaw(-2.5);
+        // Down / up and forward / backward, normalized so combined movement
+        // is not faster than moving along a single axis
+        let mut translation = Vector2::new(axis(Key::E, Key::Q), axis(Key::Space, Key::Backspace));
+        if translation.magnitude2() > 1.0 {
+            translation = translation.normalize();
         }
 
-        // Yaw Right
-        if keyboard.is_pressed(Key::D) {
-            self.yaw(2.5);
+        if pitch != 0.0 {
+            self.pitch(pitch * 1.5);
         }
 
-        // Up
-        if keyboard.is_pressed(Key::Q) {
-            self.vertical(-7.5);
+        if yaw != 0.0 {
+            self.yaw(yaw * 2.5);
         }
 
-        // Down
-        if keyboard.is_pressed(Key::E) {
-            self.vertical(7.5);
+        if translation.x != 0.0 {
+            self.vertical(translation.x * 7.5);
         }
 
-        // Move Forward
-        if keyboard.is_pressed(Key::Space) {
-            self.forward(17.5);
+        if translation.y != 0.0 {
+            self.forward(translation.y * 17.5);
         }
 
-        // Move Backward
-        if keyboard.is_pressed(Key::Backspace) {
-            self.forward(-17.5);
+    }
+
+    // Takes over the given view matrix as the current free-fly state
+    pub fn set_view(&mut self, view: Matrix4<f32>) {
+        let (rotation, position) = decompose(view);
+        self.rotation = rotation;
+        self.position = Matrix4::from_translation(position);
+        self.clamp();
+    }
+
+    // Places the free-fly camera at eye facing the target, when there is no
+    // usable direction (eye on the target or looking along up) only the
+    // position changes and the previous orientation is kept
+    pub fn look_at(&mut self, eye: Vector3<f32>, target: Vector3<f32>, up: Vector3<f32>) {
+        let direction = target - eye;
+        if direction.magnitude2() < 0.0001 || direction.cross(up).magnitude2() < 0.0001 {
+            self.position = Matrix4::from_translation(-eye);
+            self.clamp();
+
+        } else {
+            self.set_view(Matrix4::look_at(Point3::from_vec(eye), Point3::from_vec(target), up));
         }
+    }
 
+    // Moves back along the current view direction until the sphere fits
+    // into the narrower of the two fields of view
+    pub fn frame(&mut self, center: Vector3<f32>, radius: f32) {
+        let half = Rad::from(Deg(self.fov * 0.5)).0;
+        let half = half.min((half.tan() * self.aspect_ratio).atan());
+        let forward = self.rotation.conjugate() * Vector3::new(0.0, 0.0, -1.0);
+        let eye = center - forward * (radius / half.sin()).max(ORBIT_MIN_DISTANCE);
+        self.position = Matrix4::from_translation(-eye);
+        self.clamp();
     }
 
+    // Rotates around the target at the maintained orbit distance, the pitch
+    // stops short of the poles and zooming never passes through the target
+    pub fn orbit(&mut self, target: Vector3<f32>, yaw_delta: f32, pitch_delta: f32, zoom_delta: f32) {
+
+        self.orbit_target = target;
+        self.orbit_yaw = (self.orbit_yaw + yaw_delta) % 360.0;
+        self.orbit_pitch = (self.orbit_pitch + pitch_delta).max(-ORBIT_MAX_PITCH).min(ORBIT_MAX_PITCH);
+        self.orbit_distance = (self.orbit_distance - zoom_delta).max(ORBIT_MIN_DISTANCE);
+
+        let yaw = Rad::from(Deg(self.orbit_yaw)).0;
+        let pitch = Rad::from(Deg(self.orbit_pitch)).0;
+        let offset = Vector3::new(
+            pitch.cos() * yaw.cos(),
+            pitch.sin(),
+            pitch.cos() * yaw.sin()
+
+        ) * self.orbit_distance;
+
+        let target = self.orbit_target;
+        self.look_at(target + offset, target, Vector3::new(0.0, 1.0, 0.0));
+
+    }
+
+    // Moves the current state towards the given view matrix by t
+    pub fn follow(&mut self, view: Matrix4<f32>, t: f32) {
+        let (rotation, position) = decompose(view);
+        let current = Vector3::new(self.position.w[0], self.position.w[1], self.position.w[2]);
+        self.rotation = self.rotation.slerp(rotation, t);
+        self.position = Matrix4::from_translation(current.lerp(position, t));
+        self.clamp();
+    }
+
+    // A minimized window reports a zero size, keep the previous aspect ratio
     pub fn resize(&mut self, width: u32, height: u32) {
-        let aspect_ratio = width as f32 / height as f32;
-        self.projection = cgmath::perspective(Deg(self.fov), aspect_ratio, 0.01, 15000.0);
+        if width > 0 && height > 0 {
+            self.aspect_ratio = width as f32 / height as f32;
+            self.update_projection();
+        }
+    }
+
+    // Post projection scale and offset for rendering into a letterboxed or
+    // split part of the window
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.resize(viewport.width, viewport.height);
+        if viewport.scale() != self.scale || viewport.offset() != self.offset {
+            self.scale = viewport.scale();
+            self.offset = viewport.offset();
+            self.update_projection();
+        }
+    }
+
+    // Near and far plane distances, a larger near plane greatly improves
+    // depth precision for geometry close to each other
+    pub fn set_clip_range(&mut self, near: f32, far: f32) {
+        if (near, far) != self.clip_range {
+            self.clip_range = (near, far);
+            self.update_projection();
+        }
+    }
+
+    // World space ray from the near to the far plane through the given pixel
+    // of a window with the given size
+    pub fn screen_ray(&self, x: i32, y: i32, size: (u32, u32)) -> Option<(Vector3<f32>, Vector3<f32>)> {
+
+        let nx = x as f32 / size.0.max(1) as f32 * 2.0 - 1.0;
+        let ny = 1.0 - y as f32 / size.1.max(1) as f32 * 2.0;
+        let inverse = (self.projection * self.view()).invert()?;
+
+        let near = inverse * Vector4::new(nx, ny, -1.0, 1.0);
+        let far = inverse * Vector4::new(nx, ny, 1.0, 1.0);
+        Some((near.truncate() / near.w, far.truncate() / far.w))
+
     }
 
     pub fn view(&self) -> Matrix4<f32> {
@@ -125,11 +267,39 @@ impl Camera {
         self.position.w[0] += d.x[2] * s;
         self.position.w[1] += d.y[2] * s;
         self.position.w[2] += d.z[2] * s;
+        self.clamp();
     }
 
     fn vertical(&mut self, s: f32) {
         self.position.w[1] += s;
+        self.clamp();
+    }
+
+    fn update_projection(&mut self) {
+        self.projection = Matrix4::from_translation(Vector3::new(self.offset.0, self.offset.1, 0.0))
+            * Matrix4::from_nonuniform_scale(self.scale.0, self.scale.1, 1.0)
+            * cgmath::perspective(Deg(self.fov), self.aspect_ratio, self.clip_range.0, self.clip_range.1);
+    }
+
+    fn clamp(&mut self) {
+        // The view translation is the negated world position, clamping the
+        // final position per axis lets the camera slide along the bounds
+        if let Some((min, max)) = self.bounds {
+            self.position.w[0] = self.position.w[0].max(-max.x).min(-min.x);
+            self.position.w[1] = self.position.w[1].max(-max.y).min(-min.y);
+            self.position.w[2] = self.position.w[2].max(-max.z).min(-min.z);
+        }
     }
 
 }
 
+
+// Helpers --------------------------------------------------------------------
+fn decompose(view: Matrix4<f32>) -> (Quaternion<f32>, Vector3<f32>) {
+    // view = rotation * translation, so the translation is the inverse
+    // rotation applied to the view matrix offset
+    let r = Matrix3::from_cols(view.x.truncate(), view.y.truncate(), view.z.truncate());
+    let t = r.transpose() * view.w.truncate();
+    (Quaternion::from(r).normalize(), t)
+}
+
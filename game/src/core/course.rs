@@ -8,25 +8,120 @@
 
 
 // STD Dependencies -----------------------------------------------------------
+use std::cell::Cell;
+use std::cmp::Reverse;
 use std::collections::HashMap;
+use std::f32::consts::PI;
 
 
 // External Dependencies ------------------------------------------------------
-use cgmath::Vector3;
+use cgmath::{Matrix3, Matrix4, Quaternion, Vector3, InnerSpace, One, Zero};
 use renderer::{Keyboard, Key};
 
 
 // Internal Dependencies ------------------------------------------------------
-use ::core::{Mesh, Point, Segment, Intersection};
-use ::render::LineView;
+use ::core::{Axis, Mesh, Point, Random, Row, ROW_STEP, SNAP, Segment, SegmentState, SegmentType, Intersection};
+use ::core::math::{bounds, smoothstep};
+use ::render::{LineView, TextView};
 
 
 
+// Statics --------------------------------------------------------------------
+const SEAM_EPSILON: f32 = 0.01;
+const SEAM_CREASE_ANGLE: f32 = 45.0;
+const MAX_SEGMENTS: usize = 256;
+const TRACK_WIDTH: f32 = 200.0;
+const GATE_HEIGHT: f32 = 150.0;
+const GATE_BANNER: f32 = 40.0;
+const GATE_CELLS: usize = 8;
+
+// Tint of the collision overlay, translucent so the track shows through
+const COLLISION_COLOR: [f32; 4] = [1.0, 0.0, 1.0, 0.35];
+
+// Spatial index cells holding this many triangles are drawn fully red
+const TREE_DEBUG_PAIRS: f32 = 32.0;
+
+// Maximum distance between end points for attaching the active segment
+const ATTACH_DISTANCE: f32 = 250.0;
+
+// Gravity outside of all zones
+const DEFAULT_GRAVITY: (f32, f32, f32) = (0.0, -1.0, 0.0);
+
+// Distance inside a gravity or force zone over which it fades in
+const ZONE_BLEND: f32 = 50.0;
+
+// Space around a segment covered by the zones toggled for it
+const ZONE_MARGIN: f32 = 100.0;
+
+// Upwards acceleration of the force zone toggled in the editor
+const UPDRAFT: f32 = 600.0;
+
+// Spawn offset along the first row's forward and up axis
+const SPAWN_OFFSET: (f32, f32) = (10.0, 25.0);
+
+// Samples of the elevation profile summed into the total climb
+const PROFILE_SAMPLES: usize = 256;
+
+
+// Gravity Zones --------------------------------------------------------------
+#[derive(Debug, Copy, Clone)]
+pub struct GravityZone {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+    pub gravity: Vector3<f32>
+}
+
+impl GravityZone {
+
+    pub fn contains(&self, p: Vector3<f32>) -> bool {
+        self.weight(p) > 0.0
+    }
+
+    fn weight(&self, p: Vector3<f32>) -> f32 {
+        zone_weight(self.min, self.max, p)
+    }
+
+}
+
+// Constant acceleration in units per second squared, e.g. an updraft or a
+// crosswind, faded in at the boundaries like gravity zones
+#[derive(Debug, Copy, Clone)]
+pub struct ForceZone {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+    pub force: Vector3<f32>
+}
+
+impl ForceZone {
+
+    pub fn contains(&self, p: Vector3<f32>) -> bool {
+        zone_weight(self.min, self.max, p) > 0.0
+    }
+
+}
+
+
 // 3D Course Implementation ---------------------------------------------------
 pub struct Course {
     segments: Vec<Segment>,
     active_segment: usize,
-    tree: Tree
+    tree: Tree,
+    batch: bool,
+    pending: Vec<usize>,
+    clipboard: Option<SegmentState>,
+    deleted: Vec<(usize, SegmentState)>,
+    max_segments: usize,
+    row_step: f32,
+    snap: f32,
+    snap_to_grid: bool,
+    show_frames: bool,
+    show_continuation: bool,
+    refused: bool,
+    gravity_zones: Vec<GravityZone>,
+    force_zones: Vec<ForceZone>,
+    aabb: Cell<Option<(Vector3<f32>, Vector3<f32>)>>,
+    sphere: Cell<Option<(Vector3<f32>, f32)>>,
+    collision_mesh: Option<Mesh>
 }
 
 impl Course {
@@ -35,13 +130,29 @@ impl Course {
 
         // TODO handle segment indicies better
         let mut tree = Tree::new(250.0);
-        let c = Segment::new(Point::new(0.0, 0.0, 0.0, 200.0, 0.0), 90.0);
-        tree.insert(&c, 0);
+        let mut c = Segment::new(Point::new(0.0, 0.0, 0.0, TRACK_WIDTH, 0.0), 90.0);
+        tree.insert(&mut c, 0);
         let segments = vec![c];
         Self {
             segments: segments,
             active_segment: 0,
-            tree: tree
+            tree: tree,
+            batch: false,
+            pending: Vec::new(),
+            clipboard: None,
+            deleted: Vec::new(),
+            max_segments: MAX_SEGMENTS,
+            row_step: ROW_STEP,
+            snap: SNAP,
+            snap_to_grid: false,
+            show_frames: false,
+            show_continuation: true,
+            refused: false,
+            gravity_zones: Vec::new(),
+            force_zones: Vec::new(),
+            aabb: Cell::new(None),
+            sphere: Cell::new(None),
+            collision_mesh: None
         }
 
     }
@@ -50,19 +161,851 @@ impl Course {
         self.segments[0].start_point()
     }
 
+    // Position above the start facing down the track, the rotation maps the
+    // glider's local x axis onto the forward tangent and y onto the surface
+    // normal of the first row
+    pub fn spawn_transform(&self) -> (Vector3<f32>, Quaternion<f32>) {
+
+        let rows = self.segments[0].rows();
+        let forward = direction(rows, false);
+        if forward.is_zero() {
+            return (self.start_point() + Vector3::new(SPAWN_OFFSET.0, SPAWN_OFFSET.1, 0.0), Quaternion::one());
+        }
+
+        let up = rows[0].up();
+        let forward = (forward - up * up.dot(forward)).normalize();
+        let position = rows[0].pos + forward * SPAWN_OFFSET.0 + up * SPAWN_OFFSET.1;
+        (position, Quaternion::from(Matrix3::from_cols(forward, up, forward.cross(up))))
+
+    }
+
+    // Whether the last segment ends where the first one starts, the start and
+    // finish then share a single gate
+    pub fn is_closed(&self) -> bool {
+        let last = &self.segments[self.segments.len() - 1];
+        self.segments.len() > 1 && (last.end_point() - self.start_point()).magnitude2() < SEAM_EPSILON
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.active_segment
+    }
+
+    pub fn copy_active(&mut self) {
+        if let Some(s) = self.segments.get(self.active_segment) {
+            self.clipboard = Some(s.state());
+        }
+    }
+
+    // Appends the copied segment to the end point of the last segment and
+    // selects it
+    pub fn paste(&mut self) {
+        if let Some(state) = self.clipboard.clone() {
+            let start = self.segments.last().map_or(Vector3::zero(), |s| s.end_point());
+            self.begin_batch();
+            if self.append_segment(Segment::from_state(&state, start)) {
+                self.active_segment = self.segments.len() - 1;
+            }
+            self.end_batch();
+        }
+    }
+
+    // Adds a segment to the end of the course, segments beyond the maximum
+    // are refused without touching the tree or the active segment
+    pub fn append_segment(&mut self, mut segment: Segment) -> bool {
+
+        if self.segments.len() >= self.max_segments {
+            self.refused = true;
+            return false;
+        }
+
+        // New segments follow the detail level of the course
+        if segment.row_step() != self.row_step {
+            segment.set_row_step(self.row_step);
+        }
+        segment.set_snap(self.snap);
+        segment.set_snap_to_grid(self.snap_to_grid);
+
+        let id = self.segments.len();
+        segment.take_modified();
+        self.segments.push(segment);
+        self.refresh(vec![id]);
+        true
+
+    }
+
+    // Soft cap on the number of segments, existing segments beyond it are
+    // kept but no new ones can be added
+    pub fn set_max_segments(&mut self, max: usize) {
+        self.max_segments = max.max(1);
+    }
+
+    // Returns whether a segment was refused since the last call
+    pub fn take_refused(&mut self) -> bool {
+        let refused = self.refused;
+        self.refused = false;
+        refused
+    }
+
+    // Removes a segment and moves all later segments back by its extent so
+    // the next one starts where the previous one ended and the course stays
+    // connected, the last remaining segment can not be removed
+    pub fn remove_segment(&mut self, index: usize) -> bool {
+
+        if self.segments.len() < 2 || index >= self.segments.len() {
+            return false;
+        }
+
+        // Dropping the segment also releases its mesh buffer
+        let removed = self.segments.remove(index);
+        let offset = removed.start_point() - removed.end_point();
+        self.shift_segments(index, offset);
+        self.deleted.push((index, removed.state()));
+
+        if self.active_segment > index || self.active_segment == self.segments.len() {
+            self.active_segment -= 1;
+        }
+
+        self.refresh(Vec::new());
+        self.rebuild_tree();
+        true
+
+    }
+
+    // Changes the position of a segment in the course order without moving
+    // its geometry, the active segment follows along. Tree entries are keyed
+    // by index so the tree is always rebuilt in full. Removed segments can no
+    // longer be restored afterwards since their indices refer to the old order
+    pub fn move_segment(&mut self, from: usize, to: usize) -> bool {
+
+        if from == to || from >= self.segments.len() || to >= self.segments.len() {
+            return false;
+        }
+
+        let segment = self.segments.remove(from);
+        self.segments.insert(to, segment);
+        self.deleted.clear();
+
+        if self.active_segment == from {
+            self.active_segment = to;
+
+        } else if from < self.active_segment && self.active_segment <= to {
+            self.active_segment -= 1;
+
+        } else if to <= self.active_segment && self.active_segment < from {
+            self.active_segment += 1;
+        }
+
+        self.refresh(Vec::new());
+        self.rebuild_tree();
+        true
+
+    }
+
+    // Re-inserts the most recently removed segment at its former index and
+    // moves the later segments forward again
+    pub fn restore_segment(&mut self) -> bool {
+
+        if let Some((index, state)) = self.deleted.pop() {
+            let index = index.min(self.segments.len());
+            let start = if index > 0 {
+                self.segments[index - 1].end_point()
+
+            } else {
+                self.start_point()
+            };
+
+            let segment = Segment::from_state(&state, start);
+            let offset = segment.end_point() - segment.start_point();
+            self.shift_segments(index, offset);
+            self.segments.insert(index, segment);
+            self.active_segment = index;
+            self.refresh(Vec::new());
+            self.rebuild_tree();
+            true
+
+        } else {
+            false
+        }
+
+    }
+
+    // Index of the closest segment hit by the ray
+    pub fn pick(&self, ray: (Vector3<f32>, Vector3<f32>)) -> Option<usize> {
+
+        let mut closest: Option<(usize, f32)> = None;
+        for (index, segment) in self.segments.iter().enumerate() {
+            if let Some(d) = segment.mesh().raycast(ray).and_then(|hit| hit.distance_from(ray.0)) {
+                if closest.map_or(true, |(_, c)| d < c) {
+                    closest = Some((index, d));
+                }
+            }
+        }
+
+        closest.map(|(index, _)| index)
+
+    }
+
+    // Nearest segment index, the closest point on its centerline and the 3D
+    // distance to it, searches all segments so positions above or below a
+    // section of track that crosses another one resolve correctly
+    pub fn closest_point(&self, pos: Vector3<f32>) -> Option<(usize, Vector3<f32>, f32)> {
+        self.nearest_row(pos).map(|(index, point, distance, _)| (index, point, distance))
+    }
+
+    // Further from the centerline than the track is wide at the closest point
+    pub fn is_off_track(&self, pos: Vector3<f32>) -> bool {
+        self.nearest_row(pos).map_or(false, |(_, _, distance, width)| distance > width)
+    }
+
+    // Appends randomly chosen segments which continue in the direction the
+    // course currently ends in, the same seed always yields the same course
+    pub fn generate(&mut self, rng: &mut Random, count: usize) {
+
+        self.begin_batch();
+
+        let types = [
+            SegmentType::Straight,
+            SegmentType::Curve90,
+            SegmentType::Curve180,
+            SegmentType::Looping
+        ];
+
+        for _ in 0..count {
+
+            let (start, heading) = {
+                let last = &self.segments[self.segments.len() - 1];
+                (last.end_point(), direction(last.rows(), true))
+            };
+
+            // Try all orientations and keep the ones that connect smoothly
+            let mut candidates = Vec::new();
+            for typ in &types {
+                for &angle in &[0.0, 90.0, 180.0, 270.0] {
+                    for &mirrored in &[false, true] {
+                        let mut s = Segment::with_type(Point::new(start.x, start.y, start.z, TRACK_WIDTH, 0.0), *typ, angle);
+                        if mirrored {
+                            s.mirror(Axis::X);
+                        }
+                        if direction(s.rows(), false).dot(heading) > 0.99 {
+                            candidates.push((*typ, s));
+                        }
+                    }
+                }
+            }
+
+            if candidates.is_empty() {
+                break;
+            }
+
+            let index = rng.index(candidates.len());
+            let (typ, mut segment) = candidates.swap_remove(index);
+            if typ == SegmentType::Straight {
+                segment.set_length(rng.range(300.0, 900.0));
+            }
+
+            if !self.append_segment(segment) {
+                break;
+            }
+
+        }
+
+        self.end_batch();
+
+    }
+
+    // Re-tessellates every segment with the given row step, the step is
+    // clamped per segment
+    pub fn regenerate_all(&mut self, step: f32) {
+        self.begin_batch();
+        for segment in &mut self.segments {
+            segment.set_row_step(step);
+            segment.take_modified();
+        }
+        self.row_step = self.segments[0].row_step();
+        self.refresh(Vec::new());
+        self.end_batch();
+    }
+
+    pub fn row_step(&self) -> f32 {
+        self.row_step
+    }
+
+    // Endpoint movement increment of all segments, e.g. the editor grid cell
+    pub fn set_snap(&mut self, snap: f32) {
+        for segment in &mut self.segments {
+            segment.set_snap(snap);
+        }
+        self.snap = self.segments[0].snap();
+    }
+
+    // Moves the active end point of the active segment onto the closest
+    // opposite end point of any other segment and turns the segment so its
+    // direction of travel continues the neighbour's across the join
+    pub fn attach_active(&mut self) -> bool {
+
+        let (point, own, end) = {
+            let active = &self.segments[self.active_segment];
+            let end = active.is_end_active();
+            let point = if end { active.end_point() } else { active.start_point() };
+            (point, direction(active.rows(), end), end)
+        };
+
+        let mut closest: Option<(Vector3<f32>, Vector3<f32>, f32)> = None;
+        for (index, s) in self.segments.iter().enumerate() {
+            let p = if end { s.start_point() } else { s.end_point() };
+            let distance = (p - point).magnitude();
+            if index != self.active_segment && distance < ATTACH_DISTANCE && closest.map_or(true, |c| distance < c.2) {
+                closest = Some((p, direction(s.rows(), !end), distance));
+            }
+        }
+
+        if let Some((p, neighbour, _)) = closest {
+
+            // Curve control points are only defined for quarter turns, the
+            // same steps the rotate keys use
+            let yaw = if own.is_zero() || neighbour.is_zero() {
+                0.0
+
+            } else {
+                let yaw = (neighbour.z.atan2(neighbour.x) - own.z.atan2(own.x)).to_degrees();
+                (yaw / 90.0).round() * 90.0
+            };
+            self.segments[self.active_segment].attach(p, yaw);
+            true
+
+        } else {
+            false
+        }
+
+    }
+
+    pub fn add_gravity_zone(&mut self, min: Vector3<f32>, max: Vector3<f32>, gravity: Vector3<f32>) {
+        self.gravity_zones.push(GravityZone {
+            min: min,
+            max: max,
+            gravity: gravity
+        });
+    }
+
+    // Normalized gravity direction at the position, zones are blended in
+    // the order they were added so later ones take precedence
+    pub fn gravity_at(&self, p: Vector3<f32>) -> Vector3<f32> {
+        let default = Vector3::new(DEFAULT_GRAVITY.0, DEFAULT_GRAVITY.1, DEFAULT_GRAVITY.2);
+        let gravity = self.gravity_zones.iter().fold(default, |g, zone| {
+            g.lerp(zone.gravity, zone.weight(p))
+        });
+
+        if gravity.magnitude2() > 0.0001 {
+            gravity.normalize()
+
+        } else {
+            default
+        }
+    }
+
+    // Removes the zones around the active segment or adds one pulling
+    // towards its surface
+    pub fn toggle_gravity_zone(&mut self) {
+
+        let focus = self.focus_point();
+        let count = self.gravity_zones.len();
+        self.gravity_zones.retain(|zone| !zone.contains(focus));
+        if self.gravity_zones.len() < count {
+            return;
+        }
+
+        let segment = &self.segments[self.active_segment];
+        if let Some((min, max)) = segment.mesh().bounds() {
+            let up = segment.rows().iter().fold(Vector3::zero(), |sum, r| sum + r.up());
+            if !up.is_zero() {
+                let margin = Vector3::new(ZONE_MARGIN, ZONE_MARGIN, ZONE_MARGIN);
+                self.add_gravity_zone(min - margin, max + margin, -up.normalize());
+            }
+        }
+
+    }
+
+    pub fn add_force_zone(&mut self, min: Vector3<f32>, max: Vector3<f32>, force: Vector3<f32>) {
+        self.force_zones.push(ForceZone {
+            min: min,
+            max: max,
+            force: force
+        });
+    }
+
+    // Sum of all zones covering the position
+    pub fn force_at(&self, p: Vector3<f32>) -> Vector3<f32> {
+        self.force_zones.iter().fold(Vector3::zero(), |f, zone| {
+            f + zone.force * zone_weight(zone.min, zone.max, p)
+        })
+    }
+
+    // Removes the force zones around the active segment or adds an updraft
+    // covering it
+    pub fn toggle_updraft(&mut self) {
+
+        let focus = self.focus_point();
+        let count = self.force_zones.len();
+        self.force_zones.retain(|zone| !zone.contains(focus));
+        if self.force_zones.len() < count {
+            return;
+        }
+
+        if let Some((min, max)) = self.segments[self.active_segment].mesh().bounds() {
+            let margin = Vector3::new(ZONE_MARGIN, ZONE_MARGIN, ZONE_MARGIN);
+            self.add_force_zone(min - margin, max + margin, Vector3::new(0.0, UPDRAFT, 0.0));
+        }
+
+    }
+
+    // Gameplay tags of the segment, empty for indices past the end
+    pub fn tags(&self, index: usize) -> &[String] {
+        self.segments.get(index).map_or(&[], |s| s.tags())
+    }
+
+    pub fn snaps_to_grid(&self) -> bool {
+        self.snap_to_grid
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.segments.iter().map(|s| s.mesh().triangle_count()).sum()
+    }
+
+    // Selects the segment to edit, out of range indices select the last one
+    pub fn set_active(&mut self, index: usize) {
+        self.active_segment = index.min(self.segments.len().saturating_sub(1));
+    }
+
+    // Center between the end points of the segment being edited
+    pub fn focus_point(&self) -> Vector3<f32> {
+        let s = &self.segments[self.active_segment];
+        (s.start_point() + s.end_point()) * 0.5
+    }
+
+    // Axis aligned (min, max) box around all segment geometry, cached until
+    // the next edit
+    pub fn bounds(&self) -> (Vector3<f32>, Vector3<f32>) {
+
+        if let Some(aabb) = self.aabb.get() {
+            return aabb;
+        }
+
+        let vertices = self.segments.iter().flat_map(|s| s.mesh().vertices().iter());
+        let aabb = bounds(vertices).unwrap_or((Vector3::zero(), Vector3::zero()));
+
+        self.aabb.set(Some(aabb));
+        aabb
+
+    }
+
+    // Sphere (center, radius) enclosing all segment geometry, cached until
+    // the next edit
+    pub fn bounding_sphere(&self) -> (Vector3<f32>, f32) {
+
+        if let Some(sphere) = self.sphere.get() {
+            return sphere;
+        }
+
+        let (min, max) = self.bounds();
+        let center = (min + max) * 0.5;
+        let radius = self.segments.iter().flat_map(|s| s.mesh().vertices().iter()).fold(0.0f32, |r, v| {
+            r.max((v - center).magnitude())
+        });
+
+        self.sphere.set(Some((center, radius)));
+        (center, radius)
+
+    }
+
+    pub fn length(&self) -> f32 {
+        self.segments.iter().map(|s| s.length()).sum()
+    }
+
+    // Evenly spaced (distance, height) samples along the track
+    //
+    // Distance is measured as arc-length along the segment rows rather than
+    // horizontal distance, so loopings show up as a climb followed by a
+    // descent instead of collapsing onto a single distance value.
+    pub fn elevation_profile(&self, samples: usize) -> Vec<(f32, f32)> {
+
+        // Collect (distance, height) for every row
+        let mut points = Vec::new();
+        let mut distance = 0.0;
+        for segment in &self.segments {
+            let rows = segment.rows();
+            for (index, row) in rows.iter().enumerate() {
+                if index > 0 {
+                    distance += (row.pos - rows[index - 1].pos).magnitude();
+                }
+                points.push((distance, row.pos.y));
+            }
+        }
+
+        if samples == 0 || points.is_empty() {
+            return Vec::new();
+        }
+
+        let length = self.length();
+        let step = if samples > 1 {
+            length / (samples - 1) as f32
+
+        } else {
+            0.0
+        };
+
+        let mut profile = Vec::with_capacity(samples);
+        let mut index = 0;
+        for i in 0..samples {
+
+            let d = step * i as f32;
+            while index + 1 < points.len() && points[index + 1].0 < d {
+                index += 1;
+            }
+
+            let (a, b) = (points[index], points[(index + 1).min(points.len() - 1)]);
+            let t = if b.0 > a.0 {
+                ((d - a.0) / (b.0 - a.0)).max(0.0).min(1.0)
+
+            } else {
+                0.0
+            };
+
+            profile.push((d, a.1 * (1.0 - t) + b.1 * t));
+
+        }
+
+        profile
+
+    }
+
+    // Normalized difficulty score from 0.0 (a single flat straight) towards 1.0
+    //
+    // Weighting:
+    //   0.4 * curvature  - total turning angle, 1 full turn (2 PI) maps to 0.5
+    //   0.3 * loops      - number of looping segments, 1 loop maps to 0.5
+    //   0.2 * elevation  - accumulated height change, 1000 units map to 0.5
+    //   0.1 * narrowness - how much narrower the average width is than 200
+    //
+    // The first three terms are saturated via x / (x + k) so they never exceed 1.0
+    pub fn difficulty(&self) -> f32 {
+
+        let rows: Vec<&Row> = self.segments.iter().flat_map(|s| s.rows().iter()).collect();
+        if rows.is_empty() {
+            return 0.0;
+        }
+
+        let curvature: f32 = self.segments.iter().map(|s| s.curvature()).sum();
+        let loops = self.segments.iter().filter(|s| s.is_looping()).count() as f32;
+        let elevation: f32 = rows.windows(2).map(|r| (r[1].pos.y - r[0].pos.y).abs()).sum();
+        let width = rows.iter().map(|r| r.width).sum::<f32>() / rows.len() as f32;
+
+        let narrowness = (1.0 - width / 200.0).max(0.0).min(1.0);
+        0.4 * saturate(curvature, PI * 2.0)
+            + 0.3 * saturate(loops, 1.0)
+            + 0.2 * saturate(elevation, 1000.0)
+            + 0.1 * narrowness
+
+    }
+
     pub fn meshes(&mut self) -> Vec<&mut Mesh> {
         self.segments.iter_mut().map(|s| s.mesh_mut()).collect()
     }
 
+    // The triangles ray casts actually test against, built from the spatial
+    // index rather than the rendered geometry and cached until it changes
+    pub fn collision_debug_mesh(&mut self) -> &mut Mesh {
+        if self.collision_mesh.is_none() {
+            let mut mesh = Mesh::from_raw(Vec::new(), Vec::new());
+            for m in self.collision_debug_meshes() {
+                mesh.merge(&m);
+            }
+            mesh.set_color(COLLISION_COLOR);
+            self.collision_mesh = Some(mesh);
+        }
+        self.collision_mesh.as_mut().unwrap()
+    }
+
+    // One mesh per segment, welded separately so seams stay visible
+    fn collision_debug_meshes(&self) -> Vec<Mesh> {
+
+        let mut triangles: Vec<Vec<usize>> = vec![Vec::new(); self.segments.len()];
+        for (sid, tid) in self.tree.triangles() {
+            if let Some(t) = triangles.get_mut(sid) {
+                t.push(tid);
+            }
+        }
+
+        self.segments.iter().zip(triangles).filter(|&(_, ref t)| !t.is_empty()).map(|(segment, tids)| {
+            let all = segment.mesh().triangles();
+            let mut vertices = Vec::with_capacity(tids.len() * 3);
+            for t in tids.into_iter().filter_map(|tid| all.get(tid)) {
+                vertices.push(t.0);
+                vertices.push(t.1);
+                vertices.push(t.2);
+            }
+            let indices = (0..vertices.len() as u32).collect();
+            let mut mesh = Mesh::from_raw(vertices, indices);
+            mesh.weld(SEAM_EPSILON);
+            mesh
+
+        }).collect()
+
+    }
+
+    // Ray casts are disallowed while a batch is open and always miss, the
+    // tree still references triangles of the pre-batch meshes
     pub fn intersect_ray(&self, ray: (Vector3<f32>, Vector3<f32>)) -> Intersection {
-        self.tree.intersect_ray(ray, &self.segments[..])
+        self.intersect_segment(ray).map_or(Intersection::None, |(_, hit)| hit)
+    }
+
+    // Unlike intersect_ray, which returns whichever hit is found first, this
+    // tests all candidates and returns the one closest to the ray origin
+    pub fn intersect_ray_nearest(&self, ray: (Vector3<f32>, Vector3<f32>)) -> Intersection {
+        if self.batch {
+            Intersection::None
+
+        } else {
+            self.tree.intersect_ray_nearest(ray, &self.segments[..])
+        }
+    }
+
+    // Like intersect_ray, but also returns the index of the segment hit
+    pub fn intersect_segment(&self, ray: (Vector3<f32>, Vector3<f32>)) -> Option<(usize, Intersection)> {
+        if self.batch {
+            None
+
+        } else {
+            self.tree.intersect_ray(ray, &self.segments[..])
+        }
+    }
+
+    // Defers tree updates of modified segments until the batch ends
+    pub fn begin_batch(&mut self) {
+        self.batch = true;
+    }
+
+    pub fn end_batch(&mut self) {
+        self.batch = false;
+        self.rebuild_tree();
+    }
+
+    // Recreates the spatial index from scratch, tree entries are keyed by
+    // the current segment indices so this is required whenever segments are
+    // removed or inserted in between
+    pub fn rebuild_tree(&mut self) {
+        self.tree.clear();
+        self.pending.clear();
+        self.collision_mesh = None;
+        for (id, segment) in self.segments.iter_mut().enumerate() {
+            self.tree.insert(segment, id);
+        }
+    }
+
+    // Updates derived data after the given segments were regenerated, no
+    // segments means the course structure changed and all seams are redone
+    fn refresh(&mut self, modified: Vec<usize>) {
+        let seams: Vec<usize> = if modified.is_empty() {
+            for segment in &mut self.segments {
+                segment.take_modified();
+            }
+            (1..self.segments.len()).collect()
+
+        } else {
+            (1..self.segments.len()).filter(|s| modified.contains(s) || modified.contains(&(s - 1))).collect()
+        };
+        self.smooth_seams(seams);
+        self.aabb.set(None);
+        self.sphere.set(None);
+        for id in modified {
+            if !self.pending.contains(&id) {
+                self.pending.push(id);
+            }
+        }
+        if !self.batch {
+            self.update_tree();
+        }
     }
 
-    pub fn edit(&mut self, keyboard: &Keyboard) {
+    // Closest point on the row polyline of any segment, also returns the
+    // interpolated half width of the track at that point
+    fn nearest_row(&self, pos: Vector3<f32>) -> Option<(usize, Vector3<f32>, f32, f32)> {
+
+        let mut closest: Option<(usize, Vector3<f32>, f32, f32)> = None;
+        for (index, segment) in self.segments.iter().enumerate() {
+            for r in segment.rows().windows(2) {
+
+                let (a, b) = (&r[0], &r[1]);
+                let ab = b.pos - a.pos;
+                let t = if ab.magnitude2() > 0.0 {
+                    ((pos - a.pos).dot(ab) / ab.magnitude2()).max(0.0).min(1.0)
+
+                } else {
+                    0.0
+                };
+
+                let point = a.pos + ab * t;
+                let distance = (pos - point).magnitude();
+                if closest.map_or(true, |c| distance < c.2) {
+                    closest = Some((index, point, distance, a.width + (b.width - a.width) * t));
+                }
+
+            }
+        }
+
+        closest
+
+    }
+
+    // Moves all segments from the index onwards by the offset
+    fn shift_segments(&mut self, from: usize, offset: Vector3<f32>) {
+        for segment in &mut self.segments[from..] {
+            let start = segment.start_point() + offset;
+            *segment = Segment::from_state(&segment.state(), start);
+        }
+    }
+
+    fn update_tree(&mut self) {
+        if !self.pending.is_empty() {
+            self.collision_mesh = None;
+        }
+        for id in self.pending.drain(..) {
+            self.tree.remove(id);
+            self.tree.insert(&mut self.segments[id], id);
+        }
+    }
+
+    pub fn edit(&mut self, keyboard: &Keyboard, dt: f32) {
+
+        // Row coordinate frames in the debug overlay
+        if keyboard.was_pressed(Key::F2) {
+            self.show_frames = !self.show_frames;
+        }
+
+        // Ghost of where the next segment would connect
+        if keyboard.was_pressed(Key::F3) {
+            self.show_continuation = !self.show_continuation;
+        }
+
+        if keyboard.was_pressed(Key::F5) {
+            if let Some(s) = self.segments.get(self.active_segment) {
+                let stats = s.stats();
+                println!(
+                    "[Segment {}] {} rows, {} vertices, {} triangles, {:.1} length, y {:.1} to {:.1}, bank {:.1}{}",
+                    self.active_segment,
+                    stats.rows,
+                    stats.vertices,
+                    stats.triangles,
+                    stats.length,
+                    stats.min_y,
+                    stats.max_y,
+                    s.bank(),
+                    if stats.non_finite { ", NON-FINITE VERTICES" } else { "" }
+                );
+            }
+            let profile = self.elevation_profile(PROFILE_SAMPLES);
+            let climb: f32 = profile.windows(2).map(|p| (p[1].1 - p[0].1).max(0.0)).sum();
+            println!(
+                "[Course] {} segments, {:.1} length, {:.1} climb, difficulty {:.2}",
+                self.segments.len(),
+                self.length(),
+                climb,
+                self.difficulty()
+            );
+        }
+
+        if keyboard.is_pressed(Key::LControl) {
+            if keyboard.was_pressed(Key::C) {
+                self.copy_active();
+
+            } else if keyboard.was_pressed(Key::V) {
+                self.paste();
+
+            } else if keyboard.was_pressed(Key::Z) {
+                self.restore_segment();
+            }
+
+        } else if keyboard.was_pressed(Key::Delete) {
+            let index = self.active_segment;
+            self.remove_segment(index);
+        }
+
+        // Reorder the active segment within the course
+        if keyboard.was_pressed(Key::PageUp) && self.active_segment > 0 {
+            let index = self.active_segment;
+            self.move_segment(index, index - 1);
+
+        } else if keyboard.was_pressed(Key::PageDown) {
+            let index = self.active_segment;
+            self.move_segment(index, index + 1);
+        }
+
+        // Gameplay tags of the active segment
+        let tag = if keyboard.was_pressed(Key::F7) {
+            Some("checkpoint")
+
+        } else if keyboard.was_pressed(Key::F8) {
+            Some("boost_pad")
+
+        } else if keyboard.was_pressed(Key::F9) {
+            Some("finish")
+
+        } else {
+            None
+        };
+        if let Some(tag) = tag {
+            self.segments[self.active_segment].toggle_tag(tag);
+        }
+
+        // Join the active end point with the closest neighbouring segment
+        if keyboard.was_pressed(Key::F11) {
+            self.attach_active();
+        }
+
+        if keyboard.was_pressed(Key::F12) {
+            if keyboard.is_pressed(Key::LShift) {
+                self.toggle_updraft();
+
+            } else {
+                self.toggle_gravity_zone();
+            }
+        }
+
+        // Round moved endpoints onto the snap grid
+        if keyboard.was_pressed(Key::Y) {
+            self.snap_to_grid = !self.snap_to_grid;
+            for segment in &mut self.segments {
+                segment.set_snap_to_grid(self.snap_to_grid);
+            }
+        }
+
+        // Tessellation detail of the whole course, Alt scales the debug
+        // gizmos instead
+        let alt = keyboard.is_pressed(Key::LAlt);
+        if keyboard.was_pressed(Key::Plus) && !alt {
+            let step = self.row_step * 0.5;
+            self.regenerate_all(step);
+
+        } else if keyboard.was_pressed(Key::Minus) && !alt {
+            let step = self.row_step * 2.0;
+            self.regenerate_all(step);
+        }
 
         // TODO add new segment at start or end
         if self.segments.len() > self.active_segment {
-            self.segments[self.active_segment].edit(keyboard);
+            self.segments[self.active_segment].edit(keyboard, dt);
+        }
+
+        let modified: Vec<usize> = self.segments.iter_mut().enumerate().filter_map(|(i, s)| {
+            if s.take_modified() { Some(i) } else { None }
+
+        }).collect();
+
+        if !modified.is_empty() {
+            self.refresh(modified);
         }
 
         /*
@@ -110,14 +1053,216 @@ impl Course {
         */
     }
 
-    pub fn debug(&mut self, lines: &mut LineView) {
+    // Averages the normals of coincident vertices between adjacent segments so
+    // shading is continuous across seams. Vertices whose normals differ by more
+    // than SEAM_CREASE_ANGLE (e.g. at the junction of a looping) are kept as
+    // hard edges. Seam i joins the last row of segment i - 1 with the first
+    // row of segment i, only those rows are touched.
+    fn smooth_seams(&mut self, seams: Vec<usize>) {
+
+        let crease = (PI / 180.0 * SEAM_CREASE_ANGLE).cos();
+        for i in seams {
+
+            let (head, tail) = self.segments.split_at_mut(i);
+            let (a, b) = (&mut head[i - 1], &mut tail[0]);
+            let (a_row, b_row) = (a.columns() as usize + 1, b.columns() as usize + 1);
+            if a.mesh().vertices().len() < a_row || b.mesh().vertices().len() < b_row {
+                continue;
+            }
+
+            let a_start = a.mesh().vertices().len().saturating_sub(a_row);
+            a.mesh_mut().reset_normals_in(a_start..a_start + a_row);
+            b.mesh_mut().reset_normals_in(0..b_row);
+
+            let mut an = a.mesh().normals().to_vec();
+            let mut bn = b.mesh().normals().to_vec();
+            let mut changed = false;
+            for (ai, av) in a.mesh().vertices().iter().enumerate().skip(a_start) {
+                for (bi, bv) in b.mesh().vertices().iter().enumerate().take(b_row) {
+                    if (av - bv).magnitude2() < SEAM_EPSILON && an[ai].dot(bn[bi]) >= crease {
+                        let n = (an[ai] + bn[bi]).normalize();
+                        an[ai] = n;
+                        bn[bi] = n;
+                        changed = true;
+                    }
+                }
+            }
+
+            if changed {
+                a.mesh_mut().set_normals(an);
+                b.mesh_mut().set_normals(bn);
+            }
+
+        }
+
+    }
+
+    // Start and finish gates spanning the track edges, a closed course only
+    // gets a single combined gate at the start
+    pub fn markers(&self, lines: &mut LineView) {
+
+        let first = &self.segments[0];
+        let last = &self.segments[self.segments.len() - 1];
+        let start_up = first.rows()[0].up();
+
+        if self.is_closed() {
+            gate(lines, first.start_edge(), start_up, [0.0, 255.0, 0.0, 1.0], [255.0, 255.0, 255.0, 1.0], [0.0, 0.0, 0.0, 1.0]);
+
+        } else {
+            gate(lines, first.start_edge(), start_up, [0.0, 255.0, 0.0, 1.0], [0.0, 255.0, 0.0, 1.0], [255.0, 255.0, 255.0, 1.0]);
+
+            let end_up = last.rows()[last.rows().len() - 1].up();
+            gate(lines, last.end_edge(), end_up, [255.0, 0.0, 0.0, 1.0], [255.0, 255.0, 255.0, 1.0], [0.0, 0.0, 0.0, 1.0]);
+        }
+
+    }
+
+    // Gizmo lengths are multiplied by scale so they stay readable at any zoom
+    pub fn debug(&mut self, lines: &mut LineView, text: &mut TextView, view_projection: Matrix4<f32>, scale: f32) {
+
         if self.segments.len() > self.active_segment {
-            self.segments[self.active_segment].debug(lines);
+            self.segments[self.active_segment].debug(lines, self.show_frames, scale);
+            if self.show_continuation {
+                self.segments[self.active_segment].preview_continuation(lines, scale);
+            }
         }
+
+        for zone in &self.gravity_zones {
+            aabb(lines, zone.min, zone.max, [128.0, 0.0, 255.0, 1.0]);
+
+            let center = (zone.min + zone.max) * 0.5;
+            lines.add(center, center + zone.gravity * ZONE_MARGIN * scale, [255.0, 0.0, 255.0, 1.0]);
+        }
+
+        for zone in &self.force_zones {
+            aabb(lines, zone.min, zone.max, [0.0, 160.0, 255.0, 1.0]);
+            if !zone.force.is_zero() {
+                let center = (zone.min + zone.max) * 0.5;
+                arrow(lines, center, zone.force.normalize() * ZONE_MARGIN * scale, [0.0, 200.0, 255.0, 1.0]);
+            }
+        }
+
+        for (i, s) in self.segments.iter().enumerate() {
+            let color = if i == self.active_segment {
+                [255.0, 255.0, 0.0, 1.0]
+
+            } else {
+                [192.0, 192.0, 192.0, 1.0]
+            };
+            let label = if s.tags().is_empty() {
+                i.to_string()
+
+            } else {
+                format!("{} {}", i, s.tags().join(" ").to_uppercase())
+            };
+            text.add_projected(s.start_point(), view_projection, 12.0, &label, color);
+        }
+
+    }
+
+    pub fn debug_tree(&self, lines: &mut LineView) {
+        self.tree.debug(lines);
+    }
+
+    // Lines needed to draw every cell of the spatial index
+    pub fn debug_tree_lines(&self) -> usize {
+        self.tree.cells.len() * AABB_LINES
     }
 
 }
 
+// Normalized direction of travel at the start or end of the rows
+fn direction(rows: &[Row], end: bool) -> Vector3<f32> {
+    if rows.len() < 2 {
+        Vector3::zero()
+
+    } else if end {
+        (rows[rows.len() - 1].pos - rows[rows.len() - 2].pos).normalize()
+
+    } else {
+        (rows[1].pos - rows[0].pos).normalize()
+    }
+}
+
+// Two posts with a two row banner of alternating colored cells on top
+fn gate(
+    lines: &mut LineView,
+    edge: (Vector3<f32>, Vector3<f32>),
+    up: Vector3<f32>,
+    post: [f32; 4],
+    a: [f32; 4],
+    b: [f32; 4]
+) {
+
+    let (left, right) = edge;
+    let top = up * GATE_HEIGHT;
+    lines.add(left, left + top, post);
+    lines.add(right, right + top, post);
+
+    let step = (right - left) / GATE_CELLS as f32;
+    let row = up * (GATE_BANNER * 0.5);
+    for r in 0..2 {
+        for c in 0..GATE_CELLS {
+            let color = if (r + c) % 2 == 0 { a } else { b };
+            let p = left + step * c as f32 + top - row * (r + 1) as f32;
+            lines.add_strip(&[p, p + step, p + step + row, p + row, p], color);
+        }
+    }
+
+}
+
+// 0 outside, rising to 1 once ZONE_BLEND inside every face
+fn zone_weight(min: Vector3<f32>, max: Vector3<f32>, p: Vector3<f32>) -> f32 {
+    let inside = (p.x - min.x).min(max.x - p.x)
+        .min(p.y - min.y).min(max.y - p.y)
+        .min(p.z - min.z).min(max.z - p.z);
+
+    smoothstep(0.0, ZONE_BLEND, inside)
+}
+
+// Line from the origin along the vector with a two line head at the tip
+fn arrow(lines: &mut LineView, origin: Vector3<f32>, v: Vector3<f32>, color: [f32; 4]) {
+    let tip = origin + v;
+    let side = if v.cross(Vector3::unit_y()).magnitude2() > 0.0001 {
+        v.cross(Vector3::unit_y()).normalize()
+
+    } else {
+        Vector3::unit_x()
+    };
+    let back = -v * 0.2;
+    let head = side * v.magnitude() * 0.1;
+    lines.add(origin, tip, color);
+    lines.add(tip, tip + back + head, color);
+    lines.add(tip, tip + back - head, color);
+}
+
+const AABB_LINES: usize = 12;
+
+fn aabb(lines: &mut LineView, min: Vector3<f32>, max: Vector3<f32>, color: [f32; 4]) {
+    let corner = |x: bool, y: bool, z: bool| Vector3::new(
+        if x { max.x } else { min.x },
+        if y { max.y } else { min.y },
+        if z { max.z } else { min.z }
+    );
+    for &y in &[false, true] {
+        lines.add_strip(&[
+            corner(false, y, false),
+            corner(true, y, false),
+            corner(true, y, true),
+            corner(false, y, true),
+            corner(false, y, false)
+
+        ], color);
+    }
+    for &(x, z) in &[(false, false), (true, false), (true, true), (false, true)] {
+        lines.add(corner(x, false, z), corner(x, true, z), color);
+    }
+}
+
+fn saturate(value: f32, half: f32) -> f32 {
+    value / (value + half)
+}
+
 struct Tree {
     // Maps grid cells to (segment, triangle) index combinations
     cells: HashMap<(i32, i32, i32), Vec<(usize, usize)>>,
@@ -133,7 +1278,26 @@ impl Tree {
         }
     }
 
-    pub fn intersect_ray(&self, ray: (Vector3<f32>, Vector3<f32>), segments: &[Segment]) -> Intersection {
+    pub fn intersect_ray_nearest(&self, ray: (Vector3<f32>, Vector3<f32>), segments: &[Segment]) -> Intersection {
+
+        let mut nearest: Option<(f32, Intersection)> = None;
+        for (sid, tid) in self.candidates(ray) {
+            if let Some(hit) = segments[sid].mesh().intersect_ray(ray, tid) {
+                if let Some(distance) = hit.distance_from(ray.0) {
+                    if nearest.as_ref().map_or(true, |h| distance < h.0) {
+                        nearest = Some((distance, hit));
+                    }
+                }
+            }
+        }
+
+        nearest.map_or(Intersection::None, |(_, hit)| hit)
+
+    }
+
+    // Unique (segment, triangle) pairs in the cells overlapping the ray's
+    // bounding box
+    fn candidates(&self, ray: (Vector3<f32>, Vector3<f32>)) -> Vec<(usize, usize)> {
 
         let ix = (ray.0.x.min(ray.1.x) / self.size).floor() as i32;
         let iy = (ray.0.y.min(ray.1.y) / self.size).floor() as i32;
@@ -143,25 +1307,37 @@ impl Tree {
         let my = (ray.0.y.max(ray.1.y) / self.size).ceil() as i32;
         let mz = (ray.0.z.max(ray.1.z) / self.size).ceil() as i32;
 
+        let mut pairs = Vec::new();
         for x in ix..mx + 1 {
             for y in iy..my + 1 {
                 for z in iz..mz + 1 {
-                    if let Some(pairs) = self.cells.get(&(x, y, z)) {
-                        for &(sid, tid) in pairs {
-                            if let Some(t) = segments[sid].mesh().intersect_ray(ray, tid) {
-                                return t;
-                            }
-                        }
+                    if let Some(cell) = self.cells.get(&(x, y, z)) {
+                        pairs.extend_from_slice(&cell[..]);
                     }
                 }
             }
         }
+        pairs.sort();
+        pairs.dedup();
+        pairs
+
+    }
 
-        Intersection::None
+    // First hit among the candidates, not necessarily the closest one
+    pub fn intersect_ray(&self, ray: (Vector3<f32>, Vector3<f32>), segments: &[Segment]) -> Option<(usize, Intersection)> {
+        self.candidates(ray).into_iter().filter_map(|(sid, tid)| {
+            segments[sid].mesh().intersect_ray(ray, tid).map(|hit| (sid, hit))
 
+        }).next()
     }
 
-    pub fn insert(&mut self, s: &Segment, id: usize) {
+    pub fn insert(&mut self, s: &mut Segment, id: usize) {
+
+        // Triangles are stored in world space, a pending rotation animation
+        // is only displayed on top of the final geometry and not baked
+        if !s.is_animating() {
+            s.mesh_mut().bake_transform();
+        }
 
         let triangles = s.mesh().triangles();
         for (i, t) in triangles.into_iter().enumerate() {
@@ -187,9 +1363,194 @@ impl Tree {
 
     }
 
-    pub fn remove(&mut self, s: &Segment) {
-        // TODO go through all buckets
-        // TODO and remove all entries with the given segment number
+    pub fn clear(&mut self) {
+        self.cells.clear();
+    }
+
+    // Occupied cells from green to red by the number of pairs they hold, the
+    // fullest cells are drawn first when the line budget runs out
+    pub fn debug(&self, lines: &mut LineView) {
+
+        let mut cells: Vec<_> = self.cells.iter().collect();
+        cells.sort_by_key(|&(_, pairs)| Reverse(pairs.len()));
+
+        let count = lines.remaining() / AABB_LINES;
+        for (&(x, y, z), pairs) in cells.into_iter().take(count) {
+            let t = (pairs.len() as f32 / TREE_DEBUG_PAIRS).min(1.0);
+            let min = Vector3::new(x as f32, y as f32, z as f32) * self.size;
+            let max = min + Vector3::new(self.size, self.size, self.size);
+            aabb(lines, min, max, [255.0 * t, 255.0 * (1.0 - t), 0.0, 1.0]);
+        }
+
+    }
+
+    // Unique (segment, triangle) pairs across all cells
+    fn triangles(&self) -> Vec<(usize, usize)> {
+        let mut pairs: Vec<(usize, usize)> = self.cells.values().flat_map(|c| c.iter().cloned()).collect();
+        pairs.sort();
+        pairs.dedup();
+        pairs
+    }
+
+    pub fn remove(&mut self, id: usize) {
+        for cell in self.cells.values_mut() {
+            cell.retain(|&(sid, _)| sid != id);
+        }
+        self.cells.retain(|_, cell| !cell.is_empty());
+    }
+
+}
+
+
+
+// Tests ----------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+
+    use cgmath::{Vector3, InnerSpace};
+    use ::core::{Point, Segment, SegmentType};
+    use super::{Course, TRACK_WIDTH, SEAM_EPSILON};
+
+    // Initial straight followed by a second one continuing it
+    fn two_straights() -> Course {
+        let mut course = Course::new();
+        let end = course.segments[0].end_point();
+        let segment = Segment::new(Point::new(end.x, end.y, end.z, TRACK_WIDTH, 0.0), 90.0);
+        assert!(course.append_segment(segment));
+        course
+    }
+
+    fn seam_pairs(course: &Course) -> usize {
+        let (a, b) = (course.segments[0].mesh(), course.segments[1].mesh());
+        let mut pairs = 0;
+        for (av, an) in a.vertices().iter().zip(a.normals()) {
+            for (bv, bn) in b.vertices().iter().zip(b.normals()) {
+                if (av - bv).magnitude2() < SEAM_EPSILON {
+                    assert!((an - bn).magnitude() < 0.0001, "seam normals differ {:?} {:?}", an, bn);
+                    pairs += 1;
+                }
+            }
+        }
+        pairs
+    }
+
+    #[test]
+    fn test_seam_normals_match() {
+        let course = two_straights();
+        assert_eq!(seam_pairs(&course), course.segments[0].columns() as usize + 1);
+    }
+
+    #[test]
+    fn test_seam_smoothing_only_touches_seam_rows() {
+
+        let mut course = two_straights();
+        let count = course.segments[0].mesh().vertices().len();
+        let row = course.segments[0].columns() as usize + 1;
+        course.segments[0].mesh_mut().set_normals(vec![Vector3::unit_x(); count]);
+
+        // The last row is restored and smoothed, everything else is kept
+        course.refresh(vec![1]);
+        let normals = course.segments[0].mesh().normals();
+        assert!(normals[..count - row].iter().all(|n| *n == Vector3::unit_x()));
+        assert!(normals[count - row..].iter().all(|n| (n - Vector3::unit_y()).magnitude() < 0.0001));
+        assert_eq!(seam_pairs(&course), row);
+
+    }
+
+    #[test]
+    fn test_difficulty() {
+
+        // Flat straights only pick up rounding noise from the row tangents
+        assert!(Course::new().difficulty() < 0.001);
+        assert!(two_straights().difficulty() < 0.001);
+
+        let mut looping = Course::new();
+        let end = looping.segments[0].end_point();
+        let segment = Segment::with_type(Point::new(end.x, end.y, end.z, TRACK_WIDTH, 0.0), SegmentType::Looping, 90.0);
+        assert!(looping.append_segment(segment));
+        assert!(looping.difficulty() > 0.3, "looping course rated {}", looping.difficulty());
+
+    }
+
+    #[test]
+    fn test_closest_point_resolves_stacked_track() {
+
+        // Second straight running 300 units above the first one
+        let mut course = Course::new();
+        let start = course.segments[0].start_point();
+        let segment = Segment::new(Point::new(start.x, start.y + 300.0, start.z, TRACK_WIDTH, 0.0), 90.0);
+        assert!(course.append_segment(segment));
+
+        // Hovering 40 units above the middle of either one
+        for index in 0..2 {
+            let s = &course.segments[index];
+            let pos = (s.start_point() + s.end_point()) * 0.5 + Vector3::new(0.0, 40.0, 0.0);
+            let (closest, point, distance) = course.closest_point(pos).unwrap();
+            assert_eq!(closest, index);
+            assert!((distance - 40.0).abs() < 0.5, "distance {}", distance);
+            assert!(((point - pos).magnitude() - distance).abs() < 0.001);
+        }
+
+    }
+
+    #[test]
+    fn test_elevation_profile_follows_looping() {
+
+        let mut course = Course::new();
+        let end = course.segments[0].end_point();
+        let segment = Segment::with_type(Point::new(end.x, end.y, end.z, TRACK_WIDTH, 0.0), SegmentType::Looping, 90.0);
+        assert!(course.append_segment(segment));
+
+        assert!(course.elevation_profile(0).is_empty());
+
+        let profile = course.elevation_profile(64);
+        assert_eq!(profile.len(), 64);
+        assert_eq!(profile[0].0, 0.0);
+        assert!((profile[63].0 - course.length()).abs() < 0.01);
+
+        // Flat on the straight, climbs over the looping and comes back down
+        let base = course.segments[0].start_point().y;
+        let top = profile.iter().fold(base, |top, &(_, y)| top.max(y));
+        assert!((profile[0].1 - base).abs() < 0.01);
+        assert!(top > base + 100.0, "looping peaks at {}", top);
+        assert!((profile[63].1 - course.segments[1].end_point().y).abs() < 0.01);
+
+    }
+
+    #[test]
+    fn test_move_segment_drops_restore_history() {
+
+        let mut course = two_straights();
+        let end = course.segments[1].end_point();
+        let segment = Segment::new(Point::new(end.x, end.y, end.z, TRACK_WIDTH, 0.0), 90.0);
+        assert!(course.append_segment(segment));
+
+        assert!(course.remove_segment(1));
+        assert!(course.restore_segment());
+        assert_eq!(course.segments.len(), 3);
+
+        assert!(course.remove_segment(2));
+        assert!(course.move_segment(0, 1));
+        assert!(!course.restore_segment());
+        assert_eq!(course.segments.len(), 2);
+
+    }
+
+    #[test]
+    fn test_collision_debug_mesh_follows_tree() {
+
+        let mut course = Course::new();
+        let single = course.collision_debug_mesh().triangle_count();
+        assert_eq!(single, course.triangle_count());
+
+        let end = course.segments[0].end_point();
+        let segment = Segment::new(Point::new(end.x, end.y, end.z, TRACK_WIDTH, 0.0), 90.0);
+        assert!(course.append_segment(segment));
+
+        let both = course.collision_debug_mesh().triangle_count();
+        assert_eq!(both, course.triangle_count());
+        assert!(both > single);
+
     }
 
 }
@@ -7,39 +7,380 @@
 // except according to those terms.
 
 
+// STD Dependencies -----------------------------------------------------------
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+
 // External Dependencies ------------------------------------------------------
 use renderer::{Keyboard, Key};
-use cgmath::{Matrix4, Point3, Deg, Euler, Vector3, InnerSpace, Quaternion, Transform, Rotation};
+use cgmath::{Matrix4, Point3, Deg, Euler, Vector3, InnerSpace, One, Quaternion, Transform, Rotation};
 
 
 // Internal Dependencies ------------------------------------------------------
 use ::core::{Course, Intersection, Mesh};
+use ::core::math::{damp, damp_factor, is_finite_quaternion, is_finite_vector, lerp};
 use ::render::LineView;
 
 
+// Statics --------------------------------------------------------------------
+
+// Speeds below were originally tuned per frame at this rate, they are now
+// scaled by dt so the simulation is frame rate independent
+const REFERENCE_FPS: f32 = 60.0;
+
+// Smoothing rates per second, matching the former per frame factors at 60fps
+const SPEED_BLEED_RATE: f32 = 0.12;
+const TILT_RATE: f32 = 4.0;
+const AIRBORNE_TILT_RATE: f32 = 6.3;
+const MAX_ALIGN_RATE: f32 = 60.0;
+
+// Default hover spring, critically damped so it settles without bouncing
+const HOVER_STIFFNESS: f32 = 180.0;
+const HOVER_DAMPING: f32 = 26.8;
+
+// Substep of the hover spring integration and the fastest it may lift the
+// glider in units per second
+const HOVER_STEP: f32 = 1.0 / 240.0;
+const MAX_HOVER_RISE: f32 = 5.0 * REFERENCE_FPS;
+const DRIFT_DAMPING: f32 = 1.5;
+const CAMERA_RATE: f32 = 6.3;
+
+// Rates at which the chase camera moves in front of occluding geometry and
+// back out once the view is clear, and the distance it keeps from the hit
+const CAMERA_PULL_RATE: f32 = 20.0;
+const CAMERA_RELEASE_RATE: f32 = 3.0;
+const CAMERA_CLEARANCE: f32 = 5.0;
+const ANIMATION_RATE: f32 = 8.0;
+
+// Short jumps keep facing the last surface, longer flights level out
+const GAP_AIRTIME: f32 = 0.5;
+
+// Distance below the lowest course point at which a lost glider respawns
+const RESPAWN_DEPTH: f32 = 500.0;
+
+// Number of recorded simulation steps available for rewinding
+const HISTORY_SIZE: usize = 600;
+
+
+// Camera Presets -------------------------------------------------------------
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CameraMode {
+    Near,
+    Medium,
+    Far,
+    FirstPerson
+}
+
+impl CameraMode {
+
+    pub fn next(&self) -> CameraMode {
+        match *self {
+            CameraMode::Near => CameraMode::Medium,
+            CameraMode::Medium => CameraMode::Far,
+            CameraMode::Far => CameraMode::FirstPerson,
+            CameraMode::FirstPerson => CameraMode::Near
+        }
+    }
+
+    // Local (eye offset, look at target) relative to the glider transform,
+    // chase presets swing around to face the direction of travel when
+    // reversing while first person keeps looking ahead
+    fn offsets(&self, speed: f32) -> (Vector3<f32>, Vector3<f32>) {
+
+        let (offset, target) = self.chase_offsets(speed.abs());
+        if speed < 0.0 && *self != CameraMode::FirstPerson {
+            (
+                Vector3::new(-offset.x, offset.y, offset.z),
+                Vector3::new(-target.x, target.y, target.z)
+            )
+
+        } else {
+            (offset, target)
+        }
+
+    }
+
+    fn chase_offsets(&self, speed: f32) -> (Vector3<f32>, Vector3<f32>) {
+        match *self {
+            CameraMode::Near => (
+                Vector3::new(-22.0 - speed * 0.2, 10.0, -30.0 / (speed + 1.0)),
+                Vector3::new(0.0, 12.0, 0.0)
+            ),
+            CameraMode::Medium => (
+                Vector3::new(-37.0 - speed * 0.35, 15.0, -50.0 / (speed + 1.0)),
+                Vector3::new(0.0, 15.0, 0.0)
+            ),
+            CameraMode::Far => (
+                Vector3::new(-75.0 - speed * 0.5, 35.0, -80.0 / (speed + 1.0)),
+                Vector3::new(0.0, 15.0, 0.0)
+            ),
+            CameraMode::FirstPerson => (
+                Vector3::new(0.0, 0.0, 0.0),
+                Vector3::new(100.0, 0.0, 0.0)
+            )
+        }
+    }
+
+}
+
+
+// Turn Handling --------------------------------------------------------------
+
+// Turn rate in degrees per second depending on the absolute speed, scaled
+// from the glider's maximum turn rate
+#[derive(Debug, Clone)]
+pub enum TurnModel {
+    // Full rate at low speeds, inversely proportional to speed * falloff
+    // once that exceeds 1
+    InverseSpeed(f32),
+    // Same rate at any speed
+    Constant,
+    // (speed, fraction of the maximum rate) points sorted by speed, linearly
+    // interpolated and held constant past the first and last point
+    Curve(Vec<(f32, f32)>)
+}
+
+impl TurnModel {
+
+    // Cycles through the default of each model, a custom falloff or curve
+    // starts over at the default inverse speed falloff
+    pub fn next(&self) -> TurnModel {
+        match *self {
+            TurnModel::InverseSpeed(_) => TurnModel::Constant,
+            TurnModel::Constant => TurnModel::Curve(vec![(0.0, 1.0), (20.0, 0.5), (60.0, 0.2)]),
+            TurnModel::Curve(_) => TurnModel::default()
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            TurnModel::InverseSpeed(_) => "INVERSE SPEED",
+            TurnModel::Constant => "CONSTANT",
+            TurnModel::Curve(_) => "CURVE"
+        }
+    }
+
+    pub fn rate(&self, max_turn: f32, speed: f32) -> f32 {
+        let speed = speed.abs();
+        match *self {
+            TurnModel::InverseSpeed(falloff) => (max_turn / (speed * falloff).max(1.0)).min(max_turn),
+            TurnModel::Constant => max_turn,
+            TurnModel::Curve(ref points) => {
+                let fraction = match points.iter().position(|p| p.0 > speed) {
+                    Some(0) => points[0].1,
+                    Some(i) => {
+                        let (a, b) = (points[i - 1], points[i]);
+                        a.1 + (b.1 - a.1) * (speed - a.0) / (b.0 - a.0)
+                    },
+                    None => points.last().map_or(1.0, |p| p.1)
+                };
+                max_turn * fraction.max(0.0)
+            }
+        }
+    }
+
+}
+
+impl Default for TurnModel {
+    fn default() -> Self {
+        TurnModel::InverseSpeed(0.125)
+    }
+}
+
+
+// Visual Animation -----------------------------------------------------------
+
+// Tuning of the purely visual model animation, none of it affects physics
+#[derive(Debug, Copy, Clone)]
+pub struct GliderAnimation {
+    // Degrees of roll per degree per second of turning
+    pub bank: f32,
+    // Height and frequency of the hover bob while grounded
+    pub bob_height: f32,
+    pub bob_frequency: f32,
+    // Degrees of nose up pitch per unit of acceleration
+    pub pitch: f32
+}
+
+impl GliderAnimation {
+
+    // Keeps the model locked to the physics transform
+    pub fn none() -> Self {
+        Self {
+            bank: 0.0,
+            bob_height: 0.0,
+            bob_frequency: 0.0,
+            pitch: 0.0
+        }
+    }
+
+}
+
+impl Default for GliderAnimation {
+    fn default() -> Self {
+        Self {
+            bank: 0.25,
+            bob_height: 0.75,
+            bob_frequency: 1.5,
+            pitch: 4.0
+        }
+    }
+}
+
+
+// Contact Rays ---------------------------------------------------------------
+
+// Ray cast along the glider's down axis from offset + up * up to
+// offset - up * down, the offset is local with x pointing forward
+#[derive(Debug, Copy, Clone)]
+pub struct ContactRay {
+    pub offset: Vector3<f32>,
+    pub up: f32,
+    pub down: f32
+}
+
+impl ContactRay {
+
+    pub fn new(x: f32, z: f32, up: f32, down: f32) -> Self {
+        Self {
+            offset: Vector3::new(x, 0.0, z),
+            up: up,
+            down: down
+        }
+    }
+
+}
+
+// The center ray determines the hover height, the hit normals of all rays
+// are averaged into the surface orientation
+#[derive(Debug, Clone)]
+pub struct ContactRays {
+    pub center: ContactRay,
+    pub samples: Vec<ContactRay>
+}
+
+impl ContactRays {
+
+    // Four samples at the corners of the given footprint for more stable
+    // orientation on uneven surfaces
+    pub fn corners(length: f32, width: f32) -> Self {
+        let (x, z) = (length * 0.5, width * 0.5);
+        Self {
+            center: ContactRay::new(0.0, 0.0, 30.0, 30.0),
+            samples: vec![
+                ContactRay::new(x, z, 20.0, 50.0),
+                ContactRay::new(x, -z, 20.0, 50.0),
+                ContactRay::new(-x, z, 20.0, 50.0),
+                ContactRay::new(-x, -z, 20.0, 50.0)
+            ]
+        }
+    }
+
+}
+
+impl Default for ContactRays {
+    fn default() -> Self {
+        Self {
+            center: ContactRay::new(0.0, 0.0, 30.0, 30.0),
+            samples: vec![
+                ContactRay::new(40.0, 0.0, 20.0, 50.0),
+                ContactRay::new(-20.0, 0.0, 20.0, 50.0)
+            ]
+        }
+    }
+}
+
+
+// Gameplay Events ------------------------------------------------------------
+
+// Observer for e.g. a sound backend, called from Glider::update. Takeoff and
+// landing fire once per transition, the speed is reported every update
+pub trait GliderEvents {
+    fn on_land(&mut self);
+    fn on_takeoff(&mut self);
+    fn on_speed(&mut self, speed: f32);
+}
+
+// Prints each landing and takeoff along with the speed at that moment
+#[derive(Debug, Default)]
+pub struct EventLog {
+    speed: f32
+}
+
+impl GliderEvents for EventLog {
+
+    fn on_land(&mut self) {
+        println!("[Glider] Landed at {:.1}", self.speed);
+    }
+
+    fn on_takeoff(&mut self) {
+        println!("[Glider] Took off at {:.1}", self.speed);
+    }
+
+    fn on_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+}
+
+
 // 3D Glider Logic Implementation ---------------------------------------------
 pub struct Glider {
     position: Vector3<f32>,
     rotation: Quaternion<f32>,
     airborne: bool,
+    airtime: f32,
+    max_airtime: f32,
+    last_normal: Vector3<f32>,
     hover_height: f32,
     max_speed: f32,
+    max_reverse_speed: f32,
     max_gravity: f32,
     fall: f32,
     accel: f32,
     brake: f32,
     turn: f32,
-    smooth_y: f32,
+    turn_model: TurnModel,
+    hover_velocity: f32,
+    hover_response: (f32, f32),
     speed: f32,
     gravity: f32,
+    drift: Vector3<f32>,
     yaw: f32,
+    history: VecDeque<State>,
+    paused: bool,
+    camera_mode: CameraMode,
+    camera_offsets: (Vector3<f32>, Vector3<f32>),
+    camera_pull: f32,
+    camera_pull_rates: (f32, f32),
+    animation: GliderAnimation,
+    animation_state: (f32, f32, f32),
+    contact_rays: ContactRays,
+    align_rate: (f32, f32),
+    events: Option<Box<dyn GliderEvents>>,
+    current_segment: Option<usize>,
+    contact_lines: Vec<(Vector3<f32>, Vector3<f32>, [f32; 4])>,
     pub mesh: Mesh
 }
 
 impl Glider {
 
     pub fn new() -> Self {
-        Self {
+        Glider::with_mesh(Mesh::from_cube(7.0 * 0.5, 4.0 * 0.5, 5.0 * 0.5))
+    }
+
+    // Uses a custom model instead of the default box, the model is centered
+    // on its bounding box so it lines up with the physics origin the hover
+    // rays are cast from
+    pub fn with_mesh(mut mesh: Mesh) -> Self {
+
+        if let Some((min, max)) = mesh.bounds() {
+            mesh.transform = Matrix4::from_translation(-(min + max) * 0.5);
+            mesh.bake_transform();
+        }
+
+        let mut glider = Self {
             position: Vector3::new(25.0, 0.0, 25.0),
             rotation: Quaternion::from(Euler {
                 x: Deg(0.0),
@@ -47,135 +388,245 @@ impl Glider {
                 z: Deg(0.0)
             }),
             airborne: true,
+            airtime: 0.0,
+            max_airtime: 2.0,
+            last_normal: Vector3::new(0.0, 1.0, 0.0),
             hover_height: 15.0,
             max_gravity: 6.0,
             max_speed: 2.0 * 60.0 * 0.5,
+            max_reverse_speed: 2.0 * 60.0 * 0.125,
             accel: 0.025 * 60.0,
             fall: 2.0,
             brake: 0.075 * 60.0,
             turn: 1.5 * 60.0,
-            smooth_y: 0.0,
+            turn_model: TurnModel::default(),
+            hover_velocity: 0.0,
+            hover_response: (HOVER_STIFFNESS, HOVER_DAMPING),
             speed: 0.0,
             gravity: 0.0,
+            drift: Vector3::new(0.0, 0.0, 0.0),
             yaw: 0.0,
-            mesh: Mesh::from_cube(7.0 * 0.5, 4.0 * 0.5, 5.0 * 0.5)
-        }
+            history: VecDeque::with_capacity(HISTORY_SIZE),
+            paused: false,
+            camera_mode: CameraMode::Medium,
+            camera_offsets: CameraMode::Medium.offsets(0.0),
+            camera_pull: 1.0,
+            camera_pull_rates: (CAMERA_PULL_RATE, CAMERA_RELEASE_RATE),
+            animation: GliderAnimation::default(),
+            animation_state: (0.0, 0.0, 0.0),
+            contact_rays: ContactRays::default(),
+            align_rate: (TILT_RATE, AIRBORNE_TILT_RATE),
+            events: None,
+            current_segment: None,
+            contact_lines: Vec::new(),
+            mesh: mesh
+        };
+        glider.mesh.transform = glider.model_transform();
+        glider
+
+    }
+
+    // Airtime after which a glider below the course is considered lost and
+    // respawned at the start
+    pub fn set_max_airtime(&mut self, airtime: f32) {
+        self.max_airtime = airtime;
+    }
+
+    pub fn set_max_reverse_speed(&mut self, speed: f32) {
+        self.max_reverse_speed = speed.max(0.0);
+    }
+
+    // Spring constant pulling towards the hover height and damping of the
+    // vertical motion, low stiffness floats while high damping feels tight
+    pub fn set_hover_response(&mut self, stiffness: f32, damping: f32) {
+        self.hover_response = (stiffness.max(0.0), damping.max(0.0));
+    }
+
+    pub fn hover_response(&self) -> (f32, f32) {
+        self.hover_response
+    }
+
+    pub fn set_turn_model(&mut self, model: TurnModel) {
+        self.turn_model = model;
+    }
+
+    pub fn turn_model(&self) -> &TurnModel {
+        &self.turn_model
+    }
+
+    pub fn set_contact_rays(&mut self, rays: ContactRays) {
+        self.contact_rays = rays;
+    }
+
+    // Rates per second at which the up axis follows the surface normal on
+    // the ground and levels out while airborne, higher rates snap harder
+    pub fn set_align_rate(&mut self, ground: f32, air: f32) {
+        self.align_rate = (
+            ground.max(0.0).min(MAX_ALIGN_RATE),
+            air.max(0.0).min(MAX_ALIGN_RATE)
+        );
+    }
+
+    pub fn align_rate(&self) -> (f32, f32) {
+        self.align_rate
+    }
+
+    pub fn set_events(&mut self, events: Option<Box<dyn GliderEvents>>) {
+        self.events = events;
     }
 
     pub fn set_position(&mut self, position: Vector3<f32>) {
+        self.airtime = 0.0;
         self.gravity = 0.0;
+        self.drift = Vector3::new(0.0, 0.0, 0.0);
+        self.hover_velocity = 0.0;
         self.speed = 0.0;
         self.position = position;
+        self.history.clear();
+    }
+
+    // Segment below the glider according to the center contact ray, None
+    // while airborne
+    pub fn current_segment(&self) -> Option<usize> {
+        self.current_segment
+    }
+
+    // Contact rays, surface normals and sweep hits of the last update
+    pub fn contact_lines(&self) -> &[(Vector3<f32>, Vector3<f32>, [f32; 4])] {
+        &self.contact_lines
+    }
+
+    pub fn position(&self) -> Vector3<f32> {
+        self.position
+    }
+
+    pub fn up(&self) -> Vector3<f32> {
+        let m: Matrix4<f32> = self.rotation.into();
+        m.transform_vector(Vector3::new(0.0, 1.0, 0.0)).normalize()
+    }
+
+    pub fn respawn(&mut self, course: &Course) {
+        let (position, rotation) = course.spawn_transform();
+        self.set_position(position);
+        self.rotation = rotation;
+        self.airborne = true;
+        self.current_segment = None;
+        self.last_normal = rotation * Vector3::new(0.0, 1.0, 0.0);
+        self.mesh.transform = self.model_transform();
+    }
+
+    // Freezes the integration, the state resumes exactly where it stopped
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
     }
 
-    pub fn update(&mut self, dt: f32, course: &Course, lines: &mut LineView, keyboard: &Keyboard) {
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn update(&mut self, dt: f32, course: &Course, keyboard: &Keyboard) {
+
+        // Only keep displaying the collision samples, dt is per frame so
+        // nothing accumulates while paused
+        self.contact_lines.clear();
+        if self.paused {
+            self.sample(course);
+            return;
+        }
+
+        let previous = self.position;
+        let previous_speed = self.speed;
+        let was_airborne = self.airborne;
 
         // Acceleration
         if keyboard.is_pressed(Key::W) && !self.airborne {
-            self.speed += if self.speed >= self.max_speed {
-                0.0
+            self.speed = (self.speed + self.accel * dt).min(self.max_speed.max(self.speed));
 
-            } else {
-                self.accel * dt
-            };
+        // Reverse
+        } else if keyboard.is_pressed(Key::S) && !self.airborne {
+            self.speed = (self.speed - self.accel * dt).max((-self.max_reverse_speed).min(self.speed));
 
-        // Deceleration
-        } else {
+        // Deceleration towards standstill from either direction
+        } else if self.speed > 0.0 {
             self.speed = (self.speed - self.brake * dt).max(0.0);
+
+        } else {
+            self.speed = (self.speed + self.brake * dt).min(0.0);
         }
 
-        // Gravity
+        // Gravity, max_gravity acts as the terminal velocity
         if self.airborne {
-            self.gravity += if self.gravity >= self.max_gravity {
-                0.0
-
-            } else {
-                self.fall * dt
-            };
+            self.airtime += dt;
+            self.gravity = (self.gravity + self.fall * dt).min(self.max_gravity.max(self.gravity));
 
         } else {
+            self.airtime = 0.0;
             self.gravity = 0.0;
         }
 
         // Turning
         self.yaw = 0.0;
         if keyboard.is_pressed(Key::A) {
-            self.yaw = (self.turn / (self.speed * 0.125).max(1.0)).min(self.turn) * dt;
-            self.speed *= 0.998;
+            self.yaw = self.turn_model.rate(self.turn, self.speed) * dt;
+            self.speed = damp(self.speed, 0.0, SPEED_BLEED_RATE, dt);
         }
 
         if keyboard.is_pressed(Key::D) {
-            self.yaw = -(self.turn / (self.speed * 0.125).max(1.0)).min(self.turn) * dt;
-            self.speed *= 0.998;
+            self.yaw = -self.turn_model.rate(self.turn, self.speed) * dt;
+            self.speed = damp(self.speed, 0.0, SPEED_BLEED_RATE, dt);
         }
 
-        let m: Matrix4<f32> = self.rotation.into();
-        let prev_up = m.transform_vector(Vector3::new(0.0, 1.0, 0.0)).normalize();
-        let ahead = m.transform_vector(Vector3::new(40.0, 0.0, 0.0));
-        let back = m.transform_vector(Vector3::new(20.0, 0.0, 0.0));
-
-        // Perform intersection tests
-        let ar = (
-            self.position + ahead + prev_up * 20.0,
-            self.position + ahead - prev_up * 50.0
-        );
-        lines.add(ar.0, ar.1, [128.0, 0.0, 255.0, 1.0]);
-
-        let r = (
-            self.position + prev_up * 30.0,
-            self.position - prev_up * 30.0
-        );
-        lines.add(r.0, r.1, [255.0, 0.0, 255.0, 1.0]);
+        let (prev_up, normals, hit) = self.sample(course);
+        self.current_segment = hit.as_ref().map(|h| h.0);
+        if let Some((Some(p), Some(n))) = hit.map(|(_, h)| (h.point(), h.normal())) {
 
-        let br = (
-            self.position - back + prev_up * 20.0,
-            self.position - back - prev_up * 50.0
-        );
-        lines.add(br.0, br.1, [128.0, 0.0, 255.0, 1.0]);
-
-        let an = if let Intersection::PointAndNormal(_, n) = course.intersect_ray(ar) {
-            Some(n)
-
-        } else {
-            None
-        };
-
-        let bn = if let Intersection::PointAndNormal(_, n) = course.intersect_ray(br) {
-            Some(n)
-
-        } else {
-            None
-        };
-
-        if let Intersection::PointAndNormal(p, mut n) = course.intersect_ray(r) {
-
-            if an.is_some() && bn.is_some() {
-                n = (an.unwrap() + bn.unwrap() + n) / 3.0;
-                //n = an.unwrap().lerp(bn.unwrap(), 0.5);
-            }
+            let n = normals.iter().fold(n, |sum, sn| sum + sn) / (normals.len() + 1) as f32;
 
             let distance = (p - self.position).magnitude();
 
             // Debug Normal display
-            lines.add(p, p + n * 25.0, [0.0, 128.0, 128.0, 1.0]);
+            self.contact_lines.push((p, p + n * 25.0, [0.0, 128.0, 128.0, 1.0]));
 
             // Calculate new up vector
-            let desired_up = prev_up.lerp(n, 0.065 * 60.0 * dt);
-            let tilt: Quaternion<f32> = Quaternion::between_vectors(prev_up, desired_up);
-            self.rotation = tilt * self.rotation;
+            self.rotation = align(prev_up, n, self.align_rate.0, dt) * self.rotation;
+
+            // Spring towards the hover height in fixed substeps so stiff
+            // settings stay stable at low frame rates. The distance jumps
+            // when the ray first connects, so a landing starts at rest
+            if self.airborne {
+                self.hover_velocity = 0.0;
+            }
 
-            // Smoothly adjust height
-            self.smooth_y = lerp(self.smooth_y, self.hover_height - distance, 0.20 * 60.0 * dt).max(-distance).min(5.0);
-            self.position += prev_up * self.smooth_y;
+            let error = self.hover_height - distance;
+            let (stiffness, damping) = self.hover_response;
+            let (mut offset, mut remaining) = (0.0, dt);
+            while remaining > 0.0 {
+                let step = remaining.min(HOVER_STEP);
+                self.hover_velocity += (stiffness * (error - offset) - damping * self.hover_velocity) * step;
+                offset += self.hover_velocity * step;
+                remaining -= step;
+            }
+
+            // Never below the surface, clamped motion keeps no velocity so
+            // nothing is released once the clamp lets go
+            let clamped = offset.max(-distance).min(MAX_HOVER_RISE * dt);
+            if clamped != offset && dt > 0.0 {
+                self.hover_velocity = clamped / dt;
+            }
+            self.position += prev_up * clamped;
+            self.last_normal = n;
             self.airborne = false;
 
         } else {
-            // TODO
-            let n = Vector3::new(0.0, 1.0, 0.0);
-            let desired_up = prev_up.lerp(n, 0.1 * 60.0 * dt);
-            let tilt: Quaternion<f32> = Quaternion::between_vectors(prev_up, desired_up);
-            self.rotation = tilt * self.rotation;
-            self.position -= n * self.gravity;
+            let down = course.gravity_at(self.position);
+            let n = if self.airtime < GAP_AIRTIME {
+                self.last_normal
+
+            } else {
+                -down
+            };
+            self.rotation = align(prev_up, n, self.align_rate.1, dt) * self.rotation;
+            self.position += down * self.gravity * REFERENCE_FPS * dt;
             self.airborne = true;
         }
 
@@ -187,9 +638,252 @@ impl Glider {
 
         let m: Matrix4<f32> = self.rotation.into();
         let forward = m.transform_vector(Vector3::new(1.0, 0.0, 0.0)).normalize();
-        self.position += forward * self.speed;
 
-        self.mesh.transform = self.transform();
+        // Average speed over the step, exact for the constant acceleration
+        // and braking above so the distance does not depend on dt
+        self.position += forward * (previous_speed + self.speed) * 0.5 * REFERENCE_FPS * dt;
+
+        // Force zones accelerate a separate drift velocity which decays over
+        // time, both per second so the result does not depend on the frame
+        // rate. On the ground the part pushing into the surface is dropped
+        // so it can not fight the hover
+        let mut force = course.force_at(self.position);
+        if !self.airborne {
+            force -= self.last_normal * force.dot(self.last_normal).min(0.0);
+            self.drift -= self.last_normal * self.drift.dot(self.last_normal).min(0.0);
+        }
+        self.drift = (self.drift + force * dt) * (1.0 - damp_factor(DRIFT_DAMPING, dt));
+        self.position += self.drift * dt;
+
+        // Sweep the movement of this frame against the course so large steps
+        // can not tunnel through the surface between two hover samples
+        let movement = self.position - previous;
+        let sweep = course.intersect_ray_nearest((previous, self.position));
+        if let (Some(p), Some(n)) = (sweep.point(), sweep.normal()) {
+            if movement.dot(n) < 0.0 {
+                self.contact_lines.push((previous, p, [255.0, 64.0, 0.0, 1.0]));
+                self.position = p + n * self.hover_height;
+                self.last_normal = n;
+                self.gravity = 0.0;
+                self.drift -= n * self.drift.dot(n).min(0.0);
+                self.airborne = false;
+            }
+        }
+
+        // Fell off the course for good
+        if self.airborne && self.airtime > self.max_airtime && self.position.y < course.bounds().0.y - RESPAWN_DEPTH {
+            self.respawn(course);
+        }
+
+        // A single NaN would otherwise stick to the state forever, drop the
+        // step and resume from the last recorded one
+        if !self.is_finite() {
+            println!("[Glider] Non-finite state, restoring the last valid one");
+            if self.history.is_empty() {
+                self.respawn(course);
+
+            } else {
+                self.rewind(0);
+                self.drift = Vector3::new(0.0, 0.0, 0.0);
+                self.hover_velocity = 0.0;
+                self.last_normal = self.rotation * Vector3::new(0.0, 1.0, 0.0);
+            }
+            self.snap_camera();
+            return;
+        }
+
+        if let Some(ref mut events) = self.events {
+            if was_airborne && !self.airborne {
+                events.on_land();
+
+            } else if !was_airborne && self.airborne {
+                events.on_takeoff();
+            }
+            events.on_speed(self.speed);
+        }
+
+        self.record();
+        self.animate(dt, previous_speed);
+        self.mesh.transform = self.model_transform();
+
+    }
+
+    fn is_finite(&self) -> bool {
+        is_finite_vector(self.position)
+            && is_finite_quaternion(self.rotation)
+            && is_finite_vector(self.drift)
+            && is_finite_vector(self.last_normal)
+            && self.speed.is_finite()
+            && self.gravity.is_finite()
+            && self.hover_velocity.is_finite()
+            && is_finite_vector(self.camera_offsets.0)
+            && is_finite_vector(self.camera_offsets.1)
+    }
+
+    // Casts the contact rays, the sample layout is mirrored along the length
+    // of the glider while reversing so the leading rays stay in front.
+    // Returns the up vector, the normals of all sample hits and the center hit
+    // together with the index of the segment it hit
+    fn sample(&mut self, course: &Course) -> (Vector3<f32>, Vec<Vector3<f32>>, Option<(usize, Intersection)>) {
+
+        let direction = if self.speed < 0.0 { -1.0 } else { 1.0 };
+        let m: Matrix4<f32> = self.rotation.into();
+        let prev_up = m.transform_vector(Vector3::new(0.0, 1.0, 0.0)).normalize();
+        let position = self.position;
+        let ray = |r: &ContactRay| {
+            let offset = m.transform_vector(Vector3::new(r.offset.x * direction, r.offset.y, r.offset.z));
+            (
+                position + offset + prev_up * r.up,
+                position + offset - prev_up * r.down
+            )
+        };
+
+        let samples: Vec<_> = self.contact_rays.samples.iter().map(&ray).collect();
+        let mut normals = Vec::with_capacity(samples.len());
+        for r in samples {
+            self.contact_lines.push((r.0, r.1, [128.0, 0.0, 255.0, 1.0]));
+            if let Some(n) = course.intersect_ray(r).normal() {
+                normals.push(n);
+            }
+        }
+
+        let r = ray(&self.contact_rays.center);
+        self.contact_lines.push((r.0, r.1, [255.0, 0.0, 255.0, 1.0]));
+
+        (prev_up, normals, course.intersect_segment(r))
+
+    }
+
+    // Steps back through the recorded states and resumes the simulation from
+    // there, the oldest state is kept so rewinding rests at the start
+    pub fn rewind(&mut self, frames: usize) {
+
+        let count = frames.min(self.history.len().saturating_sub(1));
+        for _ in 0..count {
+            self.history.pop_back();
+        }
+
+        if let Some(state) = self.history.back().cloned() {
+            self.position = state.position;
+            self.rotation = state.rotation;
+            self.speed = state.speed;
+            self.gravity = state.gravity;
+            self.drift = state.drift;
+            self.hover_velocity = state.hover_velocity;
+            self.airborne = state.airborne;
+            self.mesh.transform = self.model_transform();
+        }
+
+    }
+
+    fn record(&mut self) {
+        if self.history.len() == HISTORY_SIZE {
+            self.history.pop_front();
+        }
+        self.history.push_back(State {
+            position: self.position,
+            rotation: self.rotation,
+            speed: self.speed,
+            gravity: self.gravity,
+            drift: self.drift,
+            hover_velocity: self.hover_velocity,
+            airborne: self.airborne
+        });
+    }
+
+    pub fn camera_mode(&self) -> CameraMode {
+        self.camera_mode
+    }
+
+    pub fn set_camera_mode(&mut self, mode: CameraMode) {
+        self.camera_mode = mode;
+    }
+
+    // Jumps to the offsets of the current preset without smoothing
+    pub fn snap_camera(&mut self) {
+        self.camera_offsets = self.camera_mode.offsets(self.speed);
+    }
+
+    // Moves the chase camera towards the offsets of the current preset and
+    // in front of any geometry between it and the glider
+    pub fn update_camera(&mut self, dt: f32, course: &Course) {
+
+        let (offset, target) = self.camera_mode.offsets(self.speed);
+        let t = damp_factor(CAMERA_RATE, dt);
+        self.camera_offsets = (
+            self.camera_offsets.0.lerp(offset, t),
+            self.camera_offsets.1.lerp(target, t)
+        );
+
+        let transform = self.transform();
+        let center = Vector3::new(transform.w[0], transform.w[1], transform.w[2]);
+        let eye = transform.transform_vector(self.camera_offsets.0);
+        let length = eye.magnitude();
+
+        let visible = if length > CAMERA_CLEARANCE {
+            course.intersect_ray_nearest((center, center + eye)).distance_from(center).map_or(1.0, |d| {
+                (d - CAMERA_CLEARANCE).max(0.0) / length
+            })
+
+        } else {
+            1.0
+        };
+
+        let rate = if visible < self.camera_pull { self.camera_pull_rates.0 } else { self.camera_pull_rates.1 };
+        self.camera_pull = lerp(self.camera_pull, visible, damp_factor(rate, dt));
+
+    }
+
+    // Rates per second for moving the camera in front of occluding geometry
+    // and back out, lower rates smooth over brief occlusions
+    pub fn set_camera_pull_rates(&mut self, pull: f32, release: f32) {
+        self.camera_pull_rates = (pull.max(0.0), release.max(0.0));
+    }
+
+    pub fn set_animation(&mut self, animation: GliderAnimation) {
+        self.animation = animation;
+    }
+
+    // Eases the (roll, pitch) of the model towards the current turn rate and
+    // acceleration and advances the bob time
+    fn animate(&mut self, dt: f32, previous_speed: f32) {
+
+        if dt <= 0.0 {
+            return;
+        }
+
+        let (roll, pitch, time) = self.animation_state;
+        let turn_rate = self.yaw / dt;
+        let acceleration = (self.speed - previous_speed) / dt;
+        let t = damp_factor(ANIMATION_RATE, dt);
+
+        self.animation_state = (
+            lerp(roll, turn_rate * self.animation.bank, t),
+            lerp(pitch, acceleration.max(0.0) * self.animation.pitch, t),
+            time + dt
+        );
+
+    }
+
+    // Physics transform with the visual animation composed on top
+    pub fn model_transform(&self) -> Matrix4<f32> {
+
+        let (roll, pitch, time) = self.animation_state;
+        let bob = if self.airborne {
+            0.0
+
+        } else {
+            (time * self.animation.bob_frequency * 2.0 * PI).sin() * self.animation.bob_height
+        };
+
+        let r: Matrix4<f32> = Quaternion::from(Euler {
+            x: Deg(-roll),
+            y: Deg(0.0),
+            z: Deg(pitch)
+
+        }).into();
+
+        self.transform() * Matrix4::from_translation(Vector3::new(0.0, bob, 0.0)) * r
 
     }
 
@@ -203,8 +897,8 @@ impl Glider {
     pub fn camera_view(&self) -> Matrix4<f32> {
         let t = self.transform();
         let c = Vector3::new(t.w[0], t.w[1], t.w[2]);
-        let target = t.transform_vector(Vector3::new(0.0, 15.0, 0.0));
-        let offset = t.transform_vector(Vector3::new(-37.0 - self.speed * 0.35, 15.0, -50.0 / (self.speed + 1.0)));
+        let target = t.transform_vector(self.camera_offsets.1);
+        let offset = t.transform_vector(self.camera_offsets.0) * self.camera_pull;
         let p = c + offset;
         let t = c + target;
 
@@ -214,14 +908,15 @@ impl Glider {
         Matrix4::look_at(Point3::new(p.x, p.y, p.z), Point3::new(t.x, t.y, t.z), up)
     }
 
-    pub fn debug(&self, lines: &mut LineView) {
+    pub fn debug(&self, lines: &mut LineView, scale: f32) {
 
         let t = self.transform();
         let p = Vector3::new(t.w[0], t.w[1], t.w[2]);
-        let o = t.transform_vector(Vector3::new( 0.0,  0.0,  0.0)) + p;
-        let x = t.transform_vector(Vector3::new(10.0,  0.0,  0.0)) + p;
-        let y = t.transform_vector(Vector3::new( 0.0, 10.0,  0.0)) + p;
-        let z = t.transform_vector(Vector3::new( 0.0,  0.0, 10.0)) + p;
+        let l = 10.0 * scale;
+        let o = t.transform_vector(Vector3::new(0.0, 0.0, 0.0)) + p;
+        let x = t.transform_vector(Vector3::new(  l, 0.0, 0.0)) + p;
+        let y = t.transform_vector(Vector3::new(0.0,   l, 0.0)) + p;
+        let z = t.transform_vector(Vector3::new(0.0, 0.0,   l)) + p;
 
         // X-Axis
         lines.add(o, x, [255.0, 0.0, 0.0, 1.0]);
@@ -236,7 +931,258 @@ impl Glider {
 
 }
 
-fn lerp(a: f32, b: f32, t: f32) -> f32 {
-    a * (1.0 - t) + b * t
+
+// Helpers --------------------------------------------------------------------
+
+// Rotation which tilts up towards the target by a frame rate independent
+// fraction of the remaining angle
+// Degenerate or non-finite input leaves the orientation unchanged
+fn align(up: Vector3<f32>, target: Vector3<f32>, rate: f32, dt: f32) -> Quaternion<f32> {
+    if target.magnitude2() < 0.000001 || !is_finite_vector(target) || !is_finite_vector(up) {
+        return Quaternion::one();
+    }
+    let desired = up.lerp(target.normalize(), damp_factor(rate, dt));
+    if desired.magnitude2() < 0.000001 {
+        Quaternion::one()
+
+    } else {
+        let q = Quaternion::between_vectors(up, desired.normalize());
+        if is_finite_quaternion(q) { q } else { Quaternion::one() }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct State {
+    position: Vector3<f32>,
+    rotation: Quaternion<f32>,
+    speed: f32,
+    gravity: f32,
+    drift: Vector3<f32>,
+    hover_velocity: f32,
+    airborne: bool
+}
+
+
+// Tests ----------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+
+    use std::f32;
+    use std::rc::Rc;
+    use std::cell::RefCell;
+    use cgmath::{Vector3, Quaternion, Euler, Deg, InnerSpace, One};
+    use renderer::Keyboard;
+    use ::core::{Course, Point, Segment};
+    use super::{align, Glider, GliderEvents, TurnModel};
+
+    const DT: f32 = 1.0 / 60.0;
+
+    fn spawned(course: &Course) -> Glider {
+        let mut glider = Glider::new();
+        glider.respawn(course);
+        glider
+    }
+
+    #[test]
+    fn test_align_to_up() {
+        let up = Vector3::new(0.0, 1.0, 0.0);
+        let q = align(up, up, 4.0, DT);
+        assert!((q.s - 1.0).abs() < 0.0001);
+        assert!(q.v.x.abs() < 0.0001 && q.v.y.abs() < 0.0001 && q.v.z.abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_align_zero_target() {
+        let up = Vector3::new(0.0, 1.0, 0.0);
+        assert_eq!(align(up, Vector3::new(0.0, 0.0, 0.0), 4.0, DT), Quaternion::one());
+    }
+
+    #[test]
+    fn test_align_nan_target() {
+        let up = Vector3::new(0.0, 1.0, 0.0);
+        assert_eq!(align(up, Vector3::new(f32::NAN, 1.0, 0.0), 4.0, DT), Quaternion::one());
+        assert_eq!(align(up, Vector3::new(0.0, f32::INFINITY, 0.0), 4.0, DT), Quaternion::one());
+    }
+
+    #[test]
+    fn test_update_is_frame_rate_independent() {
+
+        let course = Course::new();
+        let keyboard = Keyboard::new(63, ());
+        let run = |steps: usize| {
+            let mut glider = spawned(&course);
+            glider.speed = glider.max_speed * 0.1;
+            for _ in 0..steps {
+                glider.update(1.0 / steps as f32, &course, &keyboard);
+            }
+            glider.position
+        };
+
+        // One second of coasting along the straight at 30 and 120 fps
+        let (a, b) = (run(30), run(120));
+        assert!((a - b).magnitude() < 0.1, "{:?} and {:?} diverged", a, b);
+
+    }
+
+    #[test]
+    fn test_turn_model_cycle_and_rates() {
+        let constant = TurnModel::default().next();
+        assert_eq!(constant.rate(90.0, 60.0), 90.0);
+
+        let curve = constant.next();
+        assert_eq!(curve.name(), "CURVE");
+        assert_eq!(curve.rate(90.0, 0.0), 90.0);
+        assert_eq!(curve.rate(90.0, -10.0), 67.5);
+        assert_eq!(curve.rate(90.0, 100.0), 18.0);
+
+        let inverse = curve.next();
+        assert_eq!(inverse.rate(90.0, 4.0), 90.0);
+        assert_eq!(inverse.rate(90.0, 16.0), 45.0);
+    }
+
+    struct Recorder(Rc<RefCell<Vec<&'static str>>>);
+
+    impl GliderEvents for Recorder {
+        fn on_land(&mut self) {
+            self.0.borrow_mut().push("land");
+        }
+
+        fn on_takeoff(&mut self) {
+            self.0.borrow_mut().push("takeoff");
+        }
+
+        fn on_speed(&mut self, _: f32) {
+            self.0.borrow_mut().push("speed");
+        }
+    }
+
+    #[test]
+    fn test_events_fire_once_per_transition() {
+
+        let course = Course::new();
+        let keyboard = Keyboard::new(63, ());
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut glider = spawned(&course);
+        glider.set_events(Some(Box::new(Recorder(log.clone()))));
+
+        // Respawning places the glider in the air
+        glider.update(DT, &course, &keyboard);
+        glider.update(DT, &course, &keyboard);
+        assert_eq!(*log.borrow(), vec!["land", "speed", "speed"]);
+
+        // Drop the glider onto the track from above
+        log.borrow_mut().clear();
+        glider.position.y += 200.0;
+        for _ in 0..300 {
+            glider.update(DT, &course, &keyboard);
+        }
+
+        let transitions: Vec<&str> = log.borrow().iter().cloned().filter(|e| *e != "speed").collect();
+        assert_eq!(transitions, vec!["takeoff", "land"]);
+        assert_eq!(log.borrow().iter().filter(|e| **e == "speed").count(), 300);
+
+    }
+
+    // Angle in degrees between the glider's up axis and the flat track over
+    // one second after tilting it sideways by 40 degrees
+    fn align_angles(rate: f32, steps: usize) -> Vec<f32> {
+        let course = Course::new();
+        let keyboard = Keyboard::new(63, ());
+        let mut glider = spawned(&course);
+        glider.set_align_rate(rate, rate);
+        glider.rotation = glider.rotation * Quaternion::from(Euler {
+            x: Deg(40.0),
+            y: Deg(0.0),
+            z: Deg(0.0)
+        });
+        (0..steps).map(|_| {
+            glider.update(1.0 / steps as f32, &course, &keyboard);
+            glider.up().angle(Vector3::unit_y()).0.to_degrees()
+
+        }).collect()
+    }
+
+    #[test]
+    fn test_align_rate_convergence() {
+        for rate in &[1.0f32, 2.0, 4.0] {
+            for steps in &[60, 240] {
+
+                let angles = align_angles(*rate, *steps);
+                for (i, w) in angles.windows(2).enumerate() {
+                    assert!(w[1] <= w[0] + 0.001, "rate {} diverged at step {}: {:?}", rate, i, w);
+                }
+
+                // Half way through the remaining angle decays exponentially
+                let expected = 40.0 * (-rate * 0.5).exp();
+                let actual = angles[steps / 2 - 1];
+                assert!((actual - expected).abs() < expected * 0.1, "rate {} at {} fps: {} vs {}", rate, steps, actual, expected);
+
+            }
+        }
+    }
+
+    #[test]
+    fn test_fast_straight_does_not_tunnel() {
+
+        let course = Course::new();
+        let keyboard = Keyboard::new(63, ());
+        let (min, max) = course.bounds();
+        let mut glider = spawned(&course);
+        glider.set_position((min + max) * 0.5 + Vector3::new(0.0, 40.0, 0.0));
+
+        // Each step covers several times the hover ray length
+        for _ in 0..20 {
+            glider.drift = Vector3::new(0.0, -4000.0, 0.0);
+            glider.update(0.1, &course, &keyboard);
+            assert!(glider.position.y > max.y, "glider passed below the surface at {:?}", glider.position);
+        }
+
+    }
+
+    #[test]
+    fn test_sweep_lands_on_the_nearest_surface() {
+
+        // Second straight right below the first one, a single step crosses
+        // both of them
+        let mut course = Course::new();
+        let (min, max) = course.bounds();
+        let start = course.start_point();
+        let below = Segment::new(Point::new(start.x, start.y - 300.0, start.z, max.z - min.z, 0.0), 90.0);
+        assert!(course.append_segment(below));
+        assert!(course.bounds().0.y < min.y - 250.0);
+
+        let keyboard = Keyboard::new(63, ());
+        let mut glider = spawned(&course);
+        glider.set_position((min + max) * 0.5 + Vector3::new(0.0, 40.0, 0.0));
+        glider.drift = Vector3::new(0.0, -4000.0, 0.0);
+        glider.update(0.1, &course, &keyboard);
+        assert!(glider.position.y > max.y, "glider landed on the lower surface at {:?}", glider.position);
+
+    }
+
+    #[test]
+    fn test_non_finite_state_is_restored() {
+
+        let course = Course::new();
+        let keyboard = Keyboard::new(63, ());
+        let mut glider = spawned(&course);
+        for _ in 0..10 {
+            glider.update(DT, &course, &keyboard);
+        }
+
+        let recorded = glider.history.len();
+        let last = glider.history.back().cloned().unwrap();
+
+        glider.drift = Vector3::new(f32::NAN, 0.0, 0.0);
+        glider.update(DT, &course, &keyboard);
+
+        assert!(glider.is_finite());
+        assert_eq!(glider.position, last.position);
+        assert_eq!(glider.speed, last.speed);
+        assert_eq!(glider.history.len(), recorded);
+        assert!(glider.history.iter().all(|s| s.position.x.is_finite() && s.drift.x.is_finite()));
+
+    }
+
 }
 
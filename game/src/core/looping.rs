@@ -17,6 +17,7 @@ use cgmath::Vector3;
 
 // Internal Dependencies ------------------------------------------------------
 use ::core::{Point, Row};
+use ::core::math::lerp;
 
 
 // 3D Bezier Loop Implementation ----------------------------------------------
@@ -25,17 +26,19 @@ pub struct Loop {
     points: (Point, Point),
     pub angle: f32,
     width: f32,
-    radius: f32
+    radius: f32,
+    mirrored: bool
 }
 
 impl Loop {
 
-    pub fn new(a: Point, b: Point, height: f32, rotation: f32) -> Self {
+    pub fn new(a: Point, b: Point, height: f32, rotation: f32, mirrored: bool) -> Self {
         Self {
             angle: 90.0 - rotation,
             width: a.width + b.width,
             points: (a, b),
-            radius: height
+            radius: height,
+            mirrored: mirrored
         }
     }
 
@@ -47,8 +50,11 @@ impl Loop {
         let angle = self.angle * (PI / 180.0);
         let offset_angle = (self.angle - 90.0) * (PI / 180.0);
 
-        let ox = offset_angle.sin() * self.width;
-        let oz = offset_angle.cos() * self.width;
+        // Lateral offset so the exit passes beside the entry, mirrored loops
+        // shift towards the other side
+        let side = if self.mirrored { -1.0 } else { 1.0 };
+        let ox = offset_angle.sin() * self.width * side;
+        let oz = offset_angle.cos() * self.width * side;
 
         let mut t = 0.0f32;
         while t < length * 2.0 {
@@ -85,9 +91,3 @@ impl Loop {
 
 }
 
-
-// Helpers --------------------------------------------------------------------
-fn lerp(a: f32, b: f32, t: f32) -> f32 {
-    a * (1.0 - t) + b * t
-}
-
@@ -0,0 +1,152 @@
+// Copyright (c) 2017 Ivo Wetzel
+
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+// External Dependencies ------------------------------------------------------
+use cgmath::{Quaternion, Vector3};
+
+
+// Interpolation Helpers ------------------------------------------------------
+pub fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a * (1.0 - t) + b * t
+}
+
+// Hermite interpolation of x between the two edges, clamped to 0..1
+pub fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).max(0.0).min(1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// Cubic ease in and out for t in 0..1
+pub fn ease_in_out(t: f32) -> f32 {
+    let t = t.max(0.0).min(1.0);
+    if t < 0.5 {
+        4.0 * t * t * t
+
+    } else {
+        let f = -2.0 * t + 2.0;
+        1.0 - f * f * f * 0.5
+    }
+}
+
+// Moves current towards target independent of the frame rate, rate is the
+// exponential decay per second of the remaining distance
+pub fn damp(current: f32, target: f32, rate: f32, dt: f32) -> f32 {
+    lerp(current, target, damp_factor(rate, dt))
+}
+
+// Interpolation factor equivalent to damp, for use with vector lerps
+pub fn damp_factor(rate: f32, dt: f32) -> f32 {
+    1.0 - (-rate * dt).exp()
+}
+
+
+// Geometry Helpers -----------------------------------------------------------
+
+// Axis aligned (min, max) box around the points, None without any points
+pub fn bounds<'a, I: Iterator<Item=&'a Vector3<f32>>>(mut points: I) -> Option<(Vector3<f32>, Vector3<f32>)> {
+    points.next().map(|first| {
+        points.fold((*first, *first), |(min, max), v| {
+            (
+                Vector3::new(min.x.min(v.x), min.y.min(v.y), min.z.min(v.z)),
+                Vector3::new(max.x.max(v.x), max.y.max(v.y), max.z.max(v.z))
+            )
+        })
+    })
+}
+
+
+// Validation Helpers ---------------------------------------------------------
+pub fn is_finite_vector(v: Vector3<f32>) -> bool {
+    v.x.is_finite() && v.y.is_finite() && v.z.is_finite()
+}
+
+pub fn is_finite_quaternion(q: Quaternion<f32>) -> bool {
+    q.s.is_finite() && is_finite_vector(q.v)
+}
+
+
+// Tests ----------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+
+    use cgmath::Vector3;
+    use super::{bounds, lerp, smoothstep, ease_in_out, damp, damp_factor};
+
+    fn approx(a: f32, b: f32) -> bool {
+        (a - b).abs() < 0.0001
+    }
+
+    #[test]
+    fn test_lerp() {
+        assert_eq!(lerp(2.0, 6.0, 0.0), 2.0);
+        assert_eq!(lerp(2.0, 6.0, 1.0), 6.0);
+        assert_eq!(lerp(2.0, 6.0, 0.25), 3.0);
+        assert_eq!(lerp(2.0, 6.0, 1.5), 8.0);
+    }
+
+    #[test]
+    fn test_smoothstep() {
+        assert_eq!(smoothstep(10.0, 20.0, 5.0), 0.0);
+        assert_eq!(smoothstep(10.0, 20.0, 10.0), 0.0);
+        assert_eq!(smoothstep(10.0, 20.0, 15.0), 0.5);
+        assert_eq!(smoothstep(10.0, 20.0, 20.0), 1.0);
+        assert_eq!(smoothstep(10.0, 20.0, 25.0), 1.0);
+        assert!(smoothstep(10.0, 20.0, 12.5) < 0.25);
+        assert!(smoothstep(10.0, 20.0, 17.5) > 0.75);
+    }
+
+    #[test]
+    fn test_ease_in_out() {
+        assert_eq!(ease_in_out(-1.0), 0.0);
+        assert_eq!(ease_in_out(0.0), 0.0);
+        assert_eq!(ease_in_out(0.5), 0.5);
+        assert_eq!(ease_in_out(1.0), 1.0);
+        assert_eq!(ease_in_out(2.0), 1.0);
+        assert!(approx(ease_in_out(0.25) + ease_in_out(0.75), 1.0));
+    }
+
+    #[test]
+    fn test_damp_factor() {
+        assert_eq!(damp_factor(4.0, 0.0), 0.0);
+        assert!(approx(damp_factor(1.0, 1.0), 1.0 - (-1.0f32).exp()));
+        assert!(damp_factor(4.0, 100.0) > 0.9999);
+
+        // Two half steps leave the same remainder as one full step
+        let half = 1.0 - damp_factor(3.0, 0.05);
+        assert!(approx(half * half, 1.0 - damp_factor(3.0, 0.1)));
+    }
+
+    #[test]
+    fn test_damp() {
+        assert_eq!(damp(5.0, 5.0, 4.0, 0.1), 5.0);
+        assert_eq!(damp(0.0, 10.0, 4.0, 0.0), 0.0);
+
+        // Frame rate independent, 60 small steps end where one large one does
+        let mut value = 0.0;
+        for _ in 0..60 {
+            value = damp(value, 10.0, 2.0, 1.0 / 60.0);
+        }
+        assert!(approx(value, damp(0.0, 10.0, 2.0, 1.0)));
+    }
+
+    #[test]
+    fn test_bounds() {
+        assert_eq!(bounds([].iter()), None);
+
+        let points = [
+            Vector3::new(1.0, -2.0, 3.0),
+            Vector3::new(-4.0, 5.0, 0.0),
+            Vector3::new(2.0, 0.0, -6.0)
+        ];
+        assert_eq!(bounds(points[..1].iter()), Some((points[0], points[0])));
+        assert_eq!(bounds(points.iter()), Some((Vector3::new(-4.0, -2.0, -6.0), Vector3::new(2.0, 5.0, 3.0))));
+    }
+
+}
+
@@ -7,25 +7,36 @@
 // except according to those terms.
 
 
+// STD Dependencies -----------------------------------------------------------
+use std::collections::HashMap;
+use std::ops::Range;
+
+
 // External Dependencies ------------------------------------------------------
 use gfx;
 use gfx_device_gl;
+use gfx::Factory;
 use gfx::traits::FactoryExt;
 use genmesh::{Vertices, Triangulate};
 use genmesh::generators::{Plane, Cube, SharedVertex, IndexedPolygon};
-use cgmath::{Matrix4, SquareMatrix, Vector3, InnerSpace, Zero};
+use cgmath::{Matrix4, SquareMatrix, Vector3, Point3, InnerSpace, EuclideanSpace, Transform, Zero};
 
 
 // Internal Dependencies ------------------------------------------------------
+use ::core::math::bounds;
 use ::render::MeshVertex;
 
 
 // 3D Mesh Implementation -----------------------------------------------------
 pub struct Mesh {
     vectors: Vec<Vector3<f32>>,
+    normals: Vec<Vector3<f32>>,
+    tangents: Vec<Vector3<f32>>,
     indices: Vec<u32>,
     triangles: Vec<(u32, u32, u32)>,
+    colors: Vec<[f32; 4]>,
     color: [f32; 4],
+    dirty: bool,
 
     pub transform: Matrix4<f32>,
     pub buffer: Option<gfx::handle::Buffer<gfx_device_gl::Resources, MeshVertex>>,
@@ -78,22 +89,197 @@ impl Mesh {
     }
 
     pub fn from_raw(vertices: Vec<Vector3<f32>>, indices: Vec<u32>) -> Self {
-        let triangles = indices.chunks(3).map(|i| (i[0], i[1], i[2])).collect();
+        let triangles: Vec<(u32, u32, u32)> = indices.chunks(3).map(|i| (i[0], i[1], i[2])).collect();
+        let normals = vertex_normals(&vertices[..], &triangles[..]);
         Self {
             vectors: vertices,
+            normals: normals,
+            tangents: Vec::new(),
             indices: indices,
             triangles: triangles,
+            colors: Vec::new(),
             color: [1.0; 4],
+            dirty: false,
             transform: Matrix4::identity(),
             buffer: None,
             slice: None
         }
     }
 
+    // Replaces the geometry in place, the GPU buffers are only reallocated
+    // when the vertex count or the indices differ
+    pub fn set_geometry(&mut self, vertices: Vec<Vector3<f32>>, indices: Vec<u32>) {
+        if vertices.len() != self.vectors.len() || indices != self.indices {
+            self.buffer = None;
+            self.slice = None;
+        }
+        self.triangles = indices.chunks(3).map(|i| (i[0], i[1], i[2])).collect();
+        self.normals = vertex_normals(&vertices[..], &self.triangles[..]);
+        self.tangents = Vec::new();
+        self.colors = Vec::new();
+        self.vectors = vertices;
+        self.indices = indices;
+        self.dirty = true;
+    }
+
+    // Colors the whole mesh, replacing any per vertex colors
     pub fn set_color(&mut self, color: [f32; 4]) {
+        self.dirty = true;
+        self.colors = Vec::new();
+        self.color = color;
+    }
+
+    // Appends the geometry of other, placed by its own transform relative to
+    // this mesh. Both colors are kept by switching to per vertex colors.
+    pub fn merge(&mut self, other: &Mesh) {
+
+        let m = self.transform.invert().unwrap_or_else(Matrix4::identity) * other.transform;
+        let offset = self.vectors.len() as u32;
+
+        if !self.tangents.is_empty() || !other.tangents.is_empty() {
+            self.tangents.resize(self.vectors.len(), Vector3::zero());
+            for i in 0..other.vectors.len() {
+                let t = other.tangents.get(i).cloned().unwrap_or_else(Vector3::zero);
+                self.tangents.push(normalize_or_zero(m.transform_vector(t)));
+            }
+        }
+
+        if !self.colors.is_empty() || !other.colors.is_empty() || self.color != other.color {
+            let color = self.color;
+            self.colors.resize(self.vectors.len(), color);
+            for i in 0..other.vectors.len() {
+                self.colors.push(other.colors.get(i).cloned().unwrap_or(other.color));
+            }
+        }
+
+        self.vectors.extend(other.vectors.iter().map(|v| m.transform_point(Point3::from_vec(*v)).to_vec()));
+        self.normals.extend(other.normals.iter().map(|n| normalize_or_zero(m.transform_vector(*n))));
+        self.indices.extend(other.indices.iter().map(|i| i + offset));
+        self.triangles.extend(other.triangles.iter().map(|t| (t.0 + offset, t.1 + offset, t.2 + offset)));
         self.buffer = None;
         self.slice = None;
-        self.color = color;
+        self.dirty = true;
+
+    }
+
+    // Applies the current transform to all vertices and resets it to identity
+    // so CPU side queries operate in the same space the mesh is rendered in
+    pub fn bake_transform(&mut self) {
+
+        if self.transform == Matrix4::identity() {
+            return;
+        }
+
+        let m = self.transform;
+        self.vectors = self.vectors.iter().map(|v| m.transform_point(Point3::from_vec(*v)).to_vec()).collect();
+        self.normals = self.normals.iter().map(|n| normalize_or_zero(m.transform_vector(*n))).collect();
+        self.tangents = self.tangents.iter().map(|t| normalize_or_zero(m.transform_vector(*t))).collect();
+        self.transform = Matrix4::identity();
+        self.dirty = true;
+
+    }
+
+    // Merges vertices closer than epsilon into the first one encountered,
+    // triangles collapsing in the process are dropped and normals are
+    // recomputed from the shared vertices
+    pub fn weld(&mut self, epsilon: f32) {
+
+        let size = epsilon.max(0.000_001);
+        let cell = |v: &Vector3<f32>| {
+            ((v.x / size).floor() as i32, (v.y / size).floor() as i32, (v.z / size).floor() as i32)
+        };
+
+        let mut grid: HashMap<(i32, i32, i32), Vec<u32>> = HashMap::new();
+        let mut vectors: Vec<Vector3<f32>> = Vec::new();
+        let mut tangents: Vec<Vector3<f32>> = Vec::new();
+        let mut colors: Vec<[f32; 4]> = Vec::new();
+        let mut remap: Vec<u32> = Vec::with_capacity(self.vectors.len());
+
+        for (i, v) in self.vectors.iter().enumerate() {
+
+            // Neighbouring cells need to be checked too since a match may sit
+            // right across a cell boundary
+            let (cx, cy, cz) = cell(v);
+            let mut found = None;
+            'search: for x in cx - 1..cx + 2 {
+                for y in cy - 1..cy + 2 {
+                    for z in cz - 1..cz + 2 {
+                        if let Some(candidates) = grid.get(&(x, y, z)) {
+                            for &c in candidates {
+                                if (vectors[c as usize] - v).magnitude() <= epsilon {
+                                    found = Some(c);
+                                    break 'search;
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            let index = found.unwrap_or_else(|| {
+                let index = vectors.len() as u32;
+                vectors.push(*v);
+                if !self.tangents.is_empty() {
+                    tangents.push(self.tangents.get(i).cloned().unwrap_or_else(Vector3::zero));
+                }
+                if !self.colors.is_empty() {
+                    colors.push(self.colors.get(i).cloned().unwrap_or(self.color));
+                }
+                grid.entry((cx, cy, cz)).or_insert_with(Vec::new).push(index);
+                index
+            });
+            remap.push(index);
+
+        }
+
+        let triangles: Vec<(u32, u32, u32)> = self.triangles.iter().map(|t| {
+            (remap[t.0 as usize], remap[t.1 as usize], remap[t.2 as usize])
+
+        }).filter(|t| t.0 != t.1 && t.1 != t.2 && t.0 != t.2).collect();
+
+        self.indices = triangles.iter().flat_map(|t| vec![t.0, t.1, t.2]).collect();
+        self.normals = vertex_normals(&vectors[..], &triangles[..]);
+        self.triangles = triangles;
+        self.vectors = vectors;
+        self.tangents = tangents;
+        self.colors = colors;
+        self.buffer = None;
+        self.slice = None;
+
+    }
+
+    pub fn vertices(&self) -> &[Vector3<f32>] {
+        &self.vectors[..]
+    }
+
+    // Axis aligned (min, max) box around the untransformed vertices
+    pub fn bounds(&self) -> Option<(Vector3<f32>, Vector3<f32>)> {
+        bounds(self.vectors.iter())
+    }
+
+    pub fn normals(&self) -> &[Vector3<f32>] {
+        &self.normals[..]
+    }
+
+    pub fn set_normals(&mut self, normals: Vec<Vector3<f32>>) {
+        self.dirty = true;
+        self.normals = normals;
+    }
+
+    // Restores the area weighted normals derived from the triangles for the
+    // vertices in the range
+    pub fn reset_normals_in(&mut self, range: Range<usize>) {
+        let normals = vertex_normals(&self.vectors[..], &self.triangles[..]);
+        for i in range {
+            self.normals[i] = normals[i];
+        }
+        self.dirty = true;
+    }
+
+    // Per vertex tangents, vertices without one default to a zero tangent
+    pub fn set_tangents(&mut self, tangents: Vec<Vector3<f32>>) {
+        self.dirty = true;
+        self.tangents = tangents;
     }
 
     pub fn triangles(&self) -> Vec<(Vector3<f32>, Vector3<f32>, Vector3<f32>)> {
@@ -106,6 +292,10 @@ impl Mesh {
         }).collect()
     }
 
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len()
+    }
+
     pub fn intersect_ray(&self, ray: (Vector3<f32>, Vector3<f32>), tid: usize) -> Option<Intersection> {
 
         let indices = self.triangles[tid];
@@ -125,25 +315,75 @@ impl Mesh {
 
     }
 
-    pub fn is_rendered(&self) -> bool {
-        self.buffer.is_some()
+    // Tests every triangle without a spatial index and returns the hit
+    // closest to the ray origin
+    pub fn raycast(&self, ray: (Vector3<f32>, Vector3<f32>)) -> Option<Intersection> {
+        let mut nearest: Option<(f32, Intersection)> = None;
+        for hit in (0..self.triangles.len()).filter_map(|tid| self.intersect_ray(ray, tid)) {
+            if let Some(distance) = hit.distance_from(ray.0) {
+                if nearest.as_ref().map_or(true, |h| distance < h.0) {
+                    nearest = Some((distance, hit));
+                }
+            }
+        }
+        nearest.map(|(_, hit)| hit)
     }
 
-    pub fn render(&mut self, factory: &mut gfx_device_gl::Factory) {
-
-        println!("[Mesh] Rendering...");
+    pub fn is_rendered(&self) -> bool {
+        self.buffer.is_some() && !self.dirty
+    }
 
-        let vertices: Vec<MeshVertex> = self.vectors.iter().map(|v| {
+    // Uploads the vertex data, an existing buffer is updated in place and
+    // only a changed topology allocates new buffers
+    pub fn render(
+        &mut self,
+        factory: &mut gfx_device_gl::Factory,
+        encoder: &mut gfx::Encoder<gfx_device_gl::Resources, gfx_device_gl::CommandBuffer>
+    ) {
+
+        let vertices: Vec<MeshVertex> = self.vectors.iter().enumerate().map(|(i, v)| {
+            let t = self.tangents.get(i).cloned().unwrap_or_else(Vector3::zero);
+            let n = self.normals.get(i).cloned().unwrap_or_else(Vector3::zero);
             MeshVertex {
                 pos: [v.x, v.y, v.z],
-                color: self.color
+                normal: [n.x, n.y, n.z],
+                tangent: [t.x, t.y, t.z],
+                color: self.colors.get(i).cloned().unwrap_or(self.color)
             }
 
         }).collect();
 
-        let (buf, slice) = factory.create_vertex_buffer_with_slice(&vertices, &self.indices[..]);
-        self.buffer = Some(buf);
-        self.slice = Some(slice);
+        if self.buffer.is_none() || self.slice.is_none() {
+            println!("[Mesh] Rendering...");
+            match factory.create_buffer::<MeshVertex>(
+                vertices.len().max(1),
+                gfx::buffer::Role::Vertex,
+                gfx::memory::Usage::Dynamic,
+                gfx::Bind::empty()
+            ) {
+                Ok(buf) => {
+                    self.buffer = Some(buf);
+                    self.slice = Some(gfx::Slice {
+                        instances: None,
+                        start: 0,
+                        end: self.indices.len() as u32,
+                        buffer: factory.create_index_buffer(&self.indices[..]),
+                        base_vertex: 0
+                    });
+                },
+                Err(err) => {
+                    println!("[Mesh] {}", err);
+                    return;
+                }
+            }
+        }
+
+        if let Some(ref buf) = self.buffer {
+            if let Err(err) = encoder.update_buffer(buf, &vertices, 0) {
+                println!("[Mesh] {}", err);
+            }
+        }
+        self.dirty = false;
 
     }
 
@@ -151,6 +391,33 @@ impl Mesh {
 
 
 // Helpers --------------------------------------------------------------------
+fn vertex_normals(vertices: &[Vector3<f32>], triangles: &[(u32, u32, u32)]) -> Vec<Vector3<f32>> {
+
+    let mut normals = vec![Vector3::zero(); vertices.len()];
+    for &(a, b, c) in triangles {
+        let (a, b, c) = (a as usize, b as usize, c as usize);
+
+        // Un-normalized so larger triangles contribute more, oriented the
+        // same way as the normals returned by intersect_ray_triangle
+        let n = -(vertices[b] - vertices[a]).cross(vertices[c] - vertices[a]);
+        normals[a] += n;
+        normals[b] += n;
+        normals[c] += n;
+    }
+
+    normals.into_iter().map(normalize_or_zero).collect()
+
+}
+
+fn normalize_or_zero(v: Vector3<f32>) -> Vector3<f32> {
+    if v.is_zero() {
+        v
+
+    } else {
+        v.normalize()
+    }
+}
+
 pub enum Intersection {
     Degenerate,
     Parallel,
@@ -158,6 +425,29 @@ pub enum Intersection {
     None
 }
 
+impl Intersection {
+
+    pub fn point(&self) -> Option<Vector3<f32>> {
+        match *self {
+            Intersection::PointAndNormal(p, _) => Some(p),
+            _ => None
+        }
+    }
+
+    pub fn normal(&self) -> Option<Vector3<f32>> {
+        match *self {
+            Intersection::PointAndNormal(_, n) => Some(n),
+            _ => None
+        }
+    }
+
+    // None for anything but an actual hit
+    pub fn distance_from(&self, origin: Vector3<f32>) -> Option<f32> {
+        self.point().map(|p| (p - origin).magnitude())
+    }
+
+}
+
 pub fn intersect_ray_triangle(r: (Vector3<f32>, Vector3<f32>), t: &[&Vector3<f32>; 3]) -> Intersection {
 
     // get triangle edge vectors and plane normal
@@ -216,3 +506,69 @@ pub fn intersect_ray_triangle(r: (Vector3<f32>, Vector3<f32>), t: &[&Vector3<f32
 
 }
 
+
+
+// Tests ----------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+
+    use cgmath::Vector3;
+    use super::{Intersection, Mesh};
+
+    fn variants() -> Vec<Intersection> {
+        vec![
+            Intersection::Degenerate,
+            Intersection::Parallel,
+            Intersection::PointAndNormal(Vector3::new(1.0, 2.0, 3.0), Vector3::new(0.0, 1.0, 0.0)),
+            Intersection::None
+        ]
+    }
+
+    #[test]
+    fn test_weld_grid_plane() {
+
+        // Every triangle with its own copy of the shared grid points
+        let grid = Mesh::from_grid_plane(100.0, 100.0, 4, 4);
+        let vertices: Vec<_> = grid.indices.iter().map(|i| grid.vectors[*i as usize]).collect();
+        let indices = (0..vertices.len() as u32).collect();
+        let mut mesh = Mesh::from_raw(vertices, indices);
+        assert_eq!(mesh.vertices().len(), 96);
+
+        mesh.weld(0.01);
+        assert_eq!(mesh.vertices().len(), 25);
+        assert_eq!(mesh.normals().len(), 25);
+        assert_eq!(mesh.triangle_count(), 32);
+        assert!(mesh.indices.iter().all(|i| (*i as usize) < 25));
+
+    }
+
+    #[test]
+    fn test_weld_keeps_distant_vertices() {
+        let grid = Mesh::from_grid_plane(100.0, 100.0, 4, 4);
+        let mut mesh = Mesh::from_raw(grid.vectors.clone(), grid.indices.clone());
+        mesh.weld(1.0);
+        assert_eq!(mesh.vertices().len(), 25);
+        assert_eq!(mesh.indices, grid.indices);
+    }
+
+    #[test]
+    fn test_intersection_point() {
+        let points: Vec<_> = variants().iter().map(Intersection::point).collect();
+        assert_eq!(points, vec![None, None, Some(Vector3::new(1.0, 2.0, 3.0)), None]);
+    }
+
+    #[test]
+    fn test_intersection_normal() {
+        let normals: Vec<_> = variants().iter().map(Intersection::normal).collect();
+        assert_eq!(normals, vec![None, None, Some(Vector3::new(0.0, 1.0, 0.0)), None]);
+    }
+
+    #[test]
+    fn test_intersection_distance_from() {
+        let origin = Vector3::new(1.0, 2.0, 7.0);
+        let distances: Vec<_> = variants().iter().map(|i| i.distance_from(origin)).collect();
+        assert_eq!(distances, vec![None, None, Some(4.0), None]);
+    }
+
+}
+
@@ -1,19 +1,24 @@
 // Modules --------------------------------------------------------------------
 mod bezier;
+mod bindings;
 mod camera;
 mod course;
 mod glider;
 mod looping;
+mod math;
 mod mesh;
+mod random;
 mod segment;
 
 
 // Re-Exports -----------------------------------------------------------------
 pub use self::bezier::{Bezier, Point, Row};
+pub use self::bindings::BINDINGS;
 pub use self::camera::Camera;
 pub use self::course::Course;
-pub use self::glider::Glider;
+pub use self::glider::{CameraMode, ContactRays, EventLog, Glider, GliderAnimation};
 pub use self::looping::Loop;
 pub use self::mesh::{Mesh, Intersection};
-pub use self::segment::Segment;
+pub use self::random::Random;
+pub use self::segment::{Axis, ROW_STEP, ROW_STEP_MAX, SNAP, Segment, SegmentState, SegmentType};
 
@@ -0,0 +1,53 @@
+// Copyright (c) 2017 Ivo Wetzel
+
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+// External Dependencies ------------------------------------------------------
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+
+// Seeded Random Number Generator ---------------------------------------------
+
+// The same seed always produces the same sequence, independent of platform
+// and thread, so generated courses can be reproduced from the seed alone
+pub struct Random {
+    seed: u32,
+    rng: XorShiftRng
+}
+
+impl Random {
+
+    pub fn new(seed: u32) -> Self {
+        // XorShift must not be seeded with all zeros
+        Self {
+            seed: seed,
+            rng: XorShiftRng::from_seed([seed, seed ^ 0x9E37_79B9, 0x243F_6A88, 0xB7E1_5162])
+        }
+    }
+
+    pub fn seed(&self) -> u32 {
+        self.seed
+    }
+
+    // Value in min..max
+    pub fn range(&mut self, min: f32, max: f32) -> f32 {
+        if min < max {
+            self.rng.gen_range(min, max)
+
+        } else {
+            min
+        }
+    }
+
+    // Index in 0..len, len must not be zero
+    pub fn index(&mut self, len: usize) -> usize {
+        self.rng.gen_range(0, len)
+    }
+
+}
+
@@ -8,34 +8,90 @@
 
 
 // STD Dependencies -----------------------------------------------------------
+use std::mem;
 use std::f32::consts::PI;
 
 
 // External Dependencies ------------------------------------------------------
-use cgmath::{Vector3, Quaternion, Matrix4, Deg, Euler, Transform};
+use cgmath::{Vector3, Quaternion, Matrix4, Deg, Euler, Transform, InnerSpace, Zero};
 use renderer::{Keyboard, Key};
 
 
 // Internal Dependencies ------------------------------------------------------
 use ::core::{Mesh, Bezier, Point, Loop, Row};
+use ::core::math::{lerp, ease_in_out};
 use ::render::LineView;
 
 
+// Statics --------------------------------------------------------------------
+const COLUMN_SIZE: f32 = 150.0;
+const STRAIGHT_LENGTH: f32 = 500.0;
+const STRAIGHT_LENGTH_STEP: f32 = 100.0;
+const ROTATION_SPEED: f32 = 360.0;
+
+// Handle scale range for curves, outside of it the handles either collapse
+// into a kink or overshoot and cross each other
+const TIGHTNESS_MIN: f32 = 0.25;
+const TIGHTNESS_MAX: f32 = 1.5;
+const TIGHTNESS_STEP: f32 = 0.125;
+
+// Narrowest row a width profile may produce, triangulation collapses at zero
+const MIN_ROW_WIDTH: f32 = 10.0;
+
+// Turning rate in radians per unit of length at which auto banking reaches
+// its maximum, roughly that of the default 90 degree curve
+const FULL_BANK_TURN_RATE: f32 = PI * 0.5 / 500.0;
+const AUTO_BANK: f32 = 30.0;
+
+// Distance between generated rows, smaller steps give smoother geometry
+pub const ROW_STEP: f32 = 50.0;
+const ROW_STEP_MIN: f32 = 10.0;
+pub const ROW_STEP_MAX: f32 = 200.0;
+
+// Endpoint movement increment and the grid spacing endpoints snap to
+pub const SNAP: f32 = 100.0;
+const SNAP_MIN: f32 = 1.0;
+
+// Movement step multipliers while holding Ctrl / Alt
+const FINE_STEP: f32 = 0.1;
+const COARSE_STEP: f32 = 10.0;
+
+
 // 3D Course Segment Implementation -------------------------------------------
 pub struct Segment {
 
     // Segment data
     typ: SegmentType,
     angle: f32,
+    mirrored: bool,
     from: Point,
     to: Point,
 
     // Rendering
     rows: Vec<Row>,
     mesh: Mesh,
+    columns: Option<u32>,
+    straight_length: f32,
+    tightness: f32,
+    width_profile: WidthProfile,
+    row_step: f32,
+    bank: f32,
+
+    // Named gameplay properties, e.g. "checkpoint"
+    tags: Vec<String>,
+
+    // Rotation animation, the remaining angle is displayed via the mesh
+    // transform while the segment itself is already at its final rotation
+    rotation_origin: Vector3<f32>,
+    rotation_remaining: f32,
 
     // Editing
-    active_point: bool
+    modified: bool,
+    active_point: bool,
+    local_translation: bool,
+    snap: f32,
+    snap_to_grid: bool,
+    preview: Option<(Vector3<f32>, bool)>
 }
 
 impl Segment {
@@ -44,13 +100,30 @@ impl Segment {
         let mut segment = Self {
             typ: SegmentType::Straight,
             angle: 0.0,
+            mirrored: false,
             from: from.clone(),
-            to: from,
+            to: from.clone(),
 
             rows: Vec::new(),
             mesh: Mesh::from_raw(Vec::new(), Vec::new()),
+            columns: None,
+            straight_length: STRAIGHT_LENGTH,
+            tightness: 1.0,
+            width_profile: WidthProfile::Linear,
+            row_step: ROW_STEP,
+            bank: 0.0,
+
+            tags: Vec::new(),
 
-            active_point: false
+            rotation_origin: from.pos,
+            rotation_remaining: 0.0,
+
+            modified: false,
+            active_point: false,
+            local_translation: false,
+            snap: SNAP,
+            snap_to_grid: false,
+            preview: None
 
         };
         let origin = segment.from.pos;
@@ -60,9 +133,65 @@ impl Segment {
         segment
     }
 
+    // Creates a segment of the given type rotated around its start point
+    pub fn with_type(from: Point, typ: SegmentType, angle: f32) -> Self {
+        let mut segment = Segment::new(from, 0.0);
+        let origin = segment.from.pos;
+        match typ {
+            SegmentType::Straight => segment.set_to_straight(origin),
+            SegmentType::Curve90 => segment.set_to_90_curve(origin),
+            SegmentType::Curve180 => segment.set_to_180_curve(origin),
+            SegmentType::Looping => segment.set_to_looping(origin)
+        }
+        segment.rotate(origin, angle);
+        segment.generate();
+        segment
+    }
+
     // TODO support serialization
 
-    pub fn edit(&mut self, keyboard: &Keyboard) {
+    // Snapshot of the shape defining data, without mesh or editing state
+    pub fn state(&self) -> SegmentState {
+        SegmentState {
+            typ: self.typ,
+            angle: self.angle,
+            mirrored: self.mirrored,
+            from: self.from.clone(),
+            to: self.to.clone(),
+            columns: self.columns,
+            straight_length: self.straight_length,
+            tightness: self.tightness,
+            width_profile: self.width_profile,
+            row_step: self.row_step,
+            bank: self.bank,
+            tags: self.tags.clone()
+        }
+    }
+
+    // Creates a segment with the shape of the state, moved so that it starts
+    // at the given position
+    pub fn from_state(state: &SegmentState, start: Vector3<f32>) -> Self {
+        let offset = start - state.from.pos;
+        let mut segment = Segment::new(state.from.clone(), 0.0);
+        segment.typ = state.typ;
+        segment.angle = state.angle;
+        segment.mirrored = state.mirrored;
+        segment.from = state.from.clone();
+        segment.from.pos += offset;
+        segment.to = state.to.clone();
+        segment.to.pos += offset;
+        segment.columns = state.columns;
+        segment.straight_length = state.straight_length;
+        segment.tightness = state.tightness;
+        segment.width_profile = state.width_profile;
+        segment.row_step = state.row_step;
+        segment.bank = state.bank;
+        segment.tags = state.tags.clone();
+        segment.generate();
+        segment
+    }
+
+    pub fn edit(&mut self, keyboard: &Keyboard, dt: f32) {
 
         if keyboard.was_pressed(Key::G) {
             self.active_point = !self.active_point;
@@ -98,52 +227,182 @@ impl Segment {
         if keyboard.was_pressed(Key::U) {
             self.rotate(origin, -90.0);
             self.generate();
+            self.queue_rotation(origin, -90.0);
         }
 
         if keyboard.was_pressed(Key::O) {
             self.rotate(origin, 90.0);
             self.generate();
+            self.queue_rotation(origin, 90.0);
+        }
+
+        self.animate_rotation(dt);
+
+        // Ctrl+V pastes into the course instead
+        if keyboard.was_pressed(Key::V) && !keyboard.is_pressed(Key::LControl) {
+            if keyboard.is_pressed(Key::LAlt) {
+                self.reverse();
+
+            } else if keyboard.is_pressed(Key::LShift) {
+                self.mirror(Axis::Z);
+
+            } else {
+                self.mirror(Axis::X);
+            }
+        }
+
+        // Straight length, with shift the curve tightness
+        if keyboard.was_pressed(Key::N) {
+            if keyboard.is_pressed(Key::LShift) {
+                let tightness = self.tightness() - TIGHTNESS_STEP;
+                self.set_tightness(tightness);
+
+            } else {
+                let length = self.straight_length - STRAIGHT_LENGTH_STEP;
+                self.set_length(length);
+            }
+        }
+
+        if keyboard.was_pressed(Key::M) {
+            if keyboard.is_pressed(Key::LShift) {
+                let tightness = self.tightness() + TIGHTNESS_STEP;
+                self.set_tightness(tightness);
+
+            } else {
+                let length = self.straight_length + STRAIGHT_LENGTH_STEP;
+                self.set_length(length);
+            }
         }
 
+        if keyboard.was_pressed(Key::F) {
+            let profile = self.width_profile().next();
+            self.set_width_profile(profile);
+            println!("[Segment] Width profile {:?}", profile);
+        }
+
+        // Lateral mesh detail
+        if keyboard.was_pressed(Key::Key8) {
+            let columns = self.columns().saturating_sub(1);
+            self.set_columns(Some(columns));
+        }
+
+        if keyboard.was_pressed(Key::Key9) {
+            let columns = self.columns() + 1;
+            self.set_columns(Some(columns));
+        }
+
+        // Bank curves towards the inside, with shift level them again
+        if keyboard.was_pressed(Key::F10) {
+            let max = if keyboard.is_pressed(Key::LShift) { 0.0 } else { AUTO_BANK };
+            self.auto_bank(max);
+        }
+
+        // Toggle between world axis and segment local translation
+        if keyboard.was_pressed(Key::T) {
+            self.local_translation = !self.local_translation;
+        }
+
+        // Shift moves both end points and combines with either step size,
+        // fine wins when Ctrl and Alt are held together
         let shift = keyboard.is_pressed(Key::LShift);
+        let step = if keyboard.is_pressed(Key::LControl) {
+            self.snap * FINE_STEP
+
+        } else if keyboard.is_pressed(Key::LAlt) {
+            self.snap * COARSE_STEP
+
+        } else {
+            self.snap
+        };
+
         if keyboard.was_pressed(Key::I) {
-            self.translate(Vector3::new(100.0, 0.0, 0.0), false);
+            self.translate(self.translation_axis(step, 0.0), false);
             if shift {
-                self.translate(Vector3::new(100.0, 0.0, 0.0), true);
+                self.translate(self.translation_axis(step, 0.0), true);
             }
             self.generate();
         }
 
         if keyboard.was_pressed(Key::K) {
-            self.translate(Vector3::new(-100.0, 0.0, 0.0), false);
+            self.translate(self.translation_axis(-step, 0.0), false);
             if shift {
-                self.translate(Vector3::new(-100.0, 0.0, 0.0), true);
+                self.translate(self.translation_axis(-step, 0.0), true);
             }
             self.generate();
         }
 
         if keyboard.was_pressed(Key::J) {
-            self.translate(Vector3::new(0.0, 0.0, -100.0), false);
+            self.translate(self.translation_axis(0.0, -step), false);
             if shift {
-                self.translate(Vector3::new(0.0, 0.0, -100.0), true);
+                self.translate(self.translation_axis(0.0, -step), true);
             }
             self.generate();
         }
 
         if keyboard.was_pressed(Key::L) {
-            self.translate(Vector3::new(0.0, 0.0, 100.0), false);
+            self.translate(self.translation_axis(0.0, step), false);
             if shift {
-                self.translate(Vector3::new(0.0, 0.0, 100.0), true);
+                self.translate(self.translation_axis(0.0, step), true);
             }
             self.generate();
         }
 
+        // Preview the next step for as long as a movement key is held
+        self.preview = if keyboard.is_pressed(Key::I) {
+            Some((self.translation_axis(step, 0.0), shift))
+
+        } else if keyboard.is_pressed(Key::K) {
+            Some((self.translation_axis(-step, 0.0), shift))
+
+        } else if keyboard.is_pressed(Key::J) {
+            Some((self.translation_axis(0.0, -step), shift))
+
+        } else if keyboard.is_pressed(Key::L) {
+            Some((self.translation_axis(0.0, step), shift))
+
+        } else {
+            None
+        };
+
     }
 
     pub fn start_point(&self) -> Vector3<f32> {
         self.from.pos
     }
 
+    pub fn end_point(&self) -> Vector3<f32> {
+        self.to.pos
+    }
+
+    // Whether the end point is the one being edited instead of the start
+    pub fn is_end_active(&self) -> bool {
+        self.active_point
+    }
+
+    // Turns the segment by yaw degrees around its active end point and then
+    // moves it so that the active end point lands on position
+    pub fn attach(&mut self, position: Vector3<f32>, yaw: f32) {
+        let origin = if self.active_point { self.to.pos } else { self.from.pos };
+        self.rotate(origin, yaw);
+
+        let offset = position - origin;
+        self.from.pos += offset;
+        self.to.pos += offset;
+        self.generate();
+    }
+
+    // Outer (left, right) vertices of the first mesh row
+    pub fn start_edge(&self) -> (Vector3<f32>, Vector3<f32>) {
+        let v = self.mesh.vertices();
+        (v[0], v[self.columns() as usize])
+    }
+
+    // Outer (left, right) vertices of the last mesh row
+    pub fn end_edge(&self) -> (Vector3<f32>, Vector3<f32>) {
+        let v = self.mesh.vertices();
+        (v[v.len() - 1 - self.columns() as usize], v[v.len() - 1])
+    }
+
     pub fn mesh(&self) -> &Mesh {
         &self.mesh
     }
@@ -152,23 +411,385 @@ impl Segment {
         &mut self.mesh
     }
 
-    pub fn debug(&mut self, lines: &mut LineView) {
+    // Reflects the segment across the given world axis through its start
+    // point. The mesh is regenerated from the mirrored state rather than
+    // reflecting vertices, which keeps the triangle winding consistent.
+    pub fn mirror(&mut self, axis: Axis) {
+
+        let origin = self.from.pos;
+        let d = self.to.pos - origin;
+        self.to.pos = match axis {
+            Axis::X => origin + Vector3::new(-d.x, d.y, d.z),
+            Axis::Z => origin + Vector3::new(d.x, d.y, -d.z)
+        };
+
+        // Flip the direction of travel and the curve / banking handedness
+        let angle = match axis {
+            Axis::X => 180.0 - self.angle,
+            Axis::Z => 360.0 - self.angle
+        };
+        self.angle = ((angle % 360.0) + 360.0) % 360.0;
+        self.from.roll = -self.from.roll;
+        self.to.roll = -self.to.roll;
+        self.bank = -self.bank;
+        self.mirrored = !self.mirrored;
+        self.generate();
+
+    }
+
+    // Swaps both end points so the same track is driven the other way round.
+    // Rolls are measured around the direction of travel and flip with it.
+    pub fn reverse(&mut self) {
+
+        // Loops exit beside their entry, the second end point only spans
+        // the loop height
+        let (from, to) = if self.is_looping() {
+            let exit = self.rows.last().map_or(self.from.pos, |row| row.pos);
+            (exit, exit - (self.to.pos - self.from.pos))
+
+        } else {
+            (self.to.pos, self.from.pos)
+        };
+
+        let (_, _, _, ta) = self.control_points();
+        let angle = match self.typ {
+            // Curves start out opposite to their old exit direction, the
+            // handedness flips so they keep bulging towards the same side
+            SegmentType::Curve90 | SegmentType::Curve180 => {
+                self.mirrored = !self.mirrored;
+                ta
+            },
+            SegmentType::Straight | SegmentType::Looping => self.angle + 180.0
+        };
+        self.angle = ((angle % 360.0) + 360.0) % 360.0;
+
+        mem::swap(&mut self.from, &mut self.to);
+        self.from.pos = from;
+        self.to.pos = to;
+        self.from.roll = -self.from.roll;
+        self.to.roll = -self.to.roll;
+        self.bank = -self.bank;
+        self.active_point = !self.active_point;
+        self.generate();
+
+    }
+
+    // Whether the mesh transform currently displays a pending rotation
+    pub fn is_animating(&self) -> bool {
+        self.rotation_remaining != 0.0
+    }
+
+    // Sets the length used for straights, an existing straight keeps its
+    // active end point and moves the other one along its forward axis
+    pub fn set_length(&mut self, length: f32) {
+
+        self.straight_length = length.max(STRAIGHT_LENGTH_STEP);
+
+        if let SegmentType::Straight = self.typ {
+
+            let (origin, other) = if self.active_point {
+                (self.to.pos, self.from.pos)
+
+            } else {
+                (self.from.pos, self.to.pos)
+            };
+
+            let d = other - origin;
+            if !d.is_zero() {
+                let other = origin + d.normalize() * self.straight_length;
+                if self.active_point {
+                    self.from.pos = other;
+
+                } else {
+                    self.to.pos = other;
+                }
+                self.generate();
+            }
+
+        }
+
+    }
+
+    // Overrides the number of triangulated columns across the track width,
+    // None derives the count from the widest end point
+    pub fn set_columns(&mut self, columns: Option<u32>) {
+        self.columns = columns.map(|c| c.max(1));
+        self.generate();
+    }
+
+    // Scales the bezier handles of curves, lower values give tighter turns
+    // while the end points stay in place
+    pub fn set_tightness(&mut self, tightness: f32) {
+        self.tightness = tightness.max(TIGHTNESS_MIN).min(TIGHTNESS_MAX);
+        self.generate();
+    }
+
+    pub fn tightness(&self) -> f32 {
+        self.tightness
+    }
+
+    pub fn set_row_step(&mut self, step: f32) {
+        self.row_step = step.max(ROW_STEP_MIN).min(ROW_STEP_MAX);
+        self.generate();
+    }
+
+    pub fn row_step(&self) -> f32 {
+        self.row_step
+    }
+
+    pub fn tags(&self) -> &[String] {
+        &self.tags[..]
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    pub fn add_tag(&mut self, tag: &str) {
+        if !self.has_tag(tag) {
+            self.tags.push(tag.to_string());
+        }
+    }
+
+    pub fn remove_tag(&mut self, tag: &str) {
+        self.tags.retain(|t| t != tag);
+    }
+
+    pub fn toggle_tag(&mut self, tag: &str) {
+        if self.has_tag(tag) {
+            self.remove_tag(tag);
+
+        } else {
+            self.add_tag(tag);
+        }
+    }
+
+    // Distance an endpoint moves per key press
+    pub fn set_snap(&mut self, snap: f32) {
+        self.snap = snap.max(SNAP_MIN);
+    }
+
+    pub fn snap(&self) -> f32 {
+        self.snap
+    }
+
+    // Rounds moved endpoints to the nearest multiple of the snap on X and Z
+    pub fn set_snap_to_grid(&mut self, snap: bool) {
+        self.snap_to_grid = snap;
+    }
+
+    pub fn set_width_profile(&mut self, profile: WidthProfile) {
+        self.width_profile = profile;
+        self.generate();
+    }
+
+    pub fn width_profile(&self) -> WidthProfile {
+        self.width_profile
+    }
+
+    pub fn columns(&self) -> u32 {
+        self.columns.unwrap_or_else(|| {
+            let width = self.from.width.max(self.to.width) * 2.0;
+            ((width / COLUMN_SIZE).ceil() as u32).max(3)
+        })
+    }
+
+    // Returns whether the mesh was regenerated since the last call
+    pub fn take_modified(&mut self) -> bool {
+        let modified = self.modified;
+        self.modified = false;
+        modified
+    }
+
+    pub fn rows(&self) -> &[Row] {
+        &self.rows[..]
+    }
+
+    pub fn length(&self) -> f32 {
+        self.rows.windows(2).map(|r| (r[1].pos - r[0].pos).magnitude()).sum()
+    }
+
+    // Geometry summary for debugging generation and triangulation, a segment
+    // without generated geometry reports all zeros
+    pub fn stats(&self) -> SegmentStats {
+
+        let vertices = self.mesh.vertices();
+        if self.rows.is_empty() || vertices.is_empty() {
+            return SegmentStats::default();
+        }
+
+        let (min_y, max_y) = vertices.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), v| {
+            (min.min(v.y), max.max(v.y))
+        });
+
+        SegmentStats {
+            rows: self.rows.len(),
+            vertices: vertices.len(),
+            triangles: self.mesh.triangle_count(),
+            length: self.length(),
+            min_y: min_y,
+            max_y: max_y,
+            non_finite: vertices.iter().any(|v| !v.x.is_finite() || !v.y.is_finite() || !v.z.is_finite())
+        }
+
+    }
+
+    pub fn is_looping(&self) -> bool {
+        if let SegmentType::Looping = self.typ {
+            true
+
+        } else {
+            false
+        }
+    }
+
+    // Total turning angle in radians between consecutive rows
+    pub fn curvature(&self) -> f32 {
+        let tangents: Vec<Vector3<f32>> = self.rows.windows(2).map(|r| r[1].pos - r[0].pos).collect();
+        tangents.windows(2).filter(|t| !t[0].is_zero() && !t[1].is_zero()).map(|t| {
+            t[0].normalize().dot(t[1].normalize()).max(-1.0).min(1.0).acos()
+
+        }).sum()
+    }
+
+    // Signed turning angle around the up axis of the rows, positive for left
+    // turns. Loopings turn around their cross axis and report almost zero
+    pub fn turn(&self) -> f32 {
+        self.rows.windows(3).map(|r| {
+            let (a, b) = (r[1].pos - r[0].pos, r[2].pos - r[1].pos);
+            if a.is_zero() || b.is_zero() {
+                0.0
+
+            } else {
+                let (a, b) = (a.normalize(), b.normalize());
+                let angle = a.dot(b).max(-1.0).min(1.0).acos();
+                if a.cross(b).dot(r[1].binormal) < 0.0 { -angle } else { angle }
+            }
+
+        }).sum()
+    }
+
+    // Banks curves towards the inside of the turn, the roll peaks at the
+    // middle of the segment and fades out towards both end points so the
+    // seams with neighbouring segments stay level. Sharper curves bank more,
+    // straights and loopings are not banked
+    pub fn auto_bank(&mut self, max_degrees: f32) {
+        self.bank = 0.0;
+        let length = self.length();
+        if self.typ != SegmentType::Straight && self.typ != SegmentType::Looping && length > 0.0 {
+            let turn = self.turn();
+            let sharpness = (turn.abs() / length / FULL_BANK_TURN_RATE).min(1.0);
+            self.bank = max_degrees * sharpness * turn.signum();
+        }
+        self.generate();
+    }
+
+    // Peak roll in degrees added by auto banking
+    pub fn bank(&self) -> f32 {
+        self.bank
+    }
+
+    // Forward direction at the end point and a suggested end point for a
+    // straight continuation, drawn as a ghost without touching the geometry
+    pub fn preview_continuation(&self, lines: &mut LineView, scale: f32) {
+
+        // The end control point leads into the end point, loopings place it
+        // on the end point itself so fall back to the last rows
+        let (_, c, _, _) = self.control_points();
+        let mut forward = self.to.pos - c.pos;
+        if forward.is_zero() && self.rows.len() > 1 {
+            let last = self.rows.len() - 1;
+            forward = self.rows[last].pos - self.rows[last - 1].pos;
+        }
+
+        if forward.is_zero() {
+            return;
+        }
+
+        let forward = forward.normalize();
+        let next = self.to.pos + forward * self.straight_length;
+        let lead = (150.0 * scale).min(self.straight_length);
+        lines.add(self.to.pos, self.to.pos + forward * lead, [0.0, 255.0, 255.0, 1.0]);
+        lines.add(self.to.pos + forward * lead, next, [0.0, 128.0, 128.0, 0.5]);
+        lines.add(next, next + Vector3::new(0.0, 150.0 * scale, 0.0), [0.0, 255.0, 255.0, 0.5]);
+
+    }
+
+    pub fn debug(&self, lines: &mut LineView, frames: bool, scale: f32) {
 
         let (b, c, _, _) = self.control_points();
-        lines.add(b.pos, b.pos + Vector3::new(0.0, 100.0, 0.0), [255.0, 128.0, 0.0, 1.0]);
-        lines.add(c.pos, c.pos + Vector3::new(0.0, 100.0, 0.0), [0.0, 128.0, 255.0, 1.0]);
+        lines.add(b.pos, b.pos + Vector3::new(0.0, 100.0 * scale, 0.0), [255.0, 128.0, 0.0, 1.0]);
+        lines.add(c.pos, c.pos + Vector3::new(0.0, 100.0 * scale, 0.0), [0.0, 128.0, 255.0, 1.0]);
 
         if self.active_point {
-            lines.add(self.to.pos,  self.to.pos + Vector3::new(0.0, 300.0, 0.0), [255.0, 255.0, 0.0, 1.0]);
+            lines.add(self.to.pos,  self.to.pos + Vector3::new(0.0, 300.0 * scale, 0.0), [255.0, 255.0, 0.0, 1.0]);
 
         } else {
-            lines.add(self.from.pos,  self.from.pos + Vector3::new(0.0, 300.0, 0.0), [255.0, 255.0, 0.0, 1.0]);
+            lines.add(self.from.pos,  self.from.pos + Vector3::new(0.0, 300.0 * scale, 0.0), [255.0, 255.0, 0.0, 1.0]);
         }
 
-        for row in &self.rows {
-            let angle = 0.0f32;
-            let o = (row.binormal * angle.cos() + row.normal * angle.sin()) * 50.0;
-            lines.add(row.pos, row.pos + o, [0.0, 255.0, 0.0, 1.0]);
+        if frames {
+
+            // Full rolled frame per row, the same basis triangulate uses
+            let last = self.rows.len().saturating_sub(1);
+            for (index, row) in self.rows.iter().enumerate() {
+
+                let angle = PI * 0.5 + (PI / 180.0) * row.roll;
+                let across = row.binormal * angle.cos() + row.normal * angle.sin();
+                let up = row.binormal * (angle - PI * 0.5).cos() + row.normal * (angle - PI * 0.5).sin();
+                let forward = if index < last {
+                    self.rows[index + 1].pos - row.pos
+
+                } else if index > 0 {
+                    row.pos - self.rows[index - 1].pos
+
+                } else {
+                    Vector3::zero()
+                };
+
+                lines.add(row.pos, row.pos + across * 50.0 * scale, [255.0, 0.0, 0.0, 1.0]);
+                lines.add(row.pos, row.pos + up * 50.0 * scale, [0.0, 255.0, 0.0, 1.0]);
+                if !forward.is_zero() {
+                    lines.add(row.pos, row.pos + forward.normalize() * 50.0 * scale, [0.0, 0.0, 255.0, 1.0]);
+                }
+
+            }
+
+        } else {
+            for row in &self.rows {
+                let angle = 0.0f32;
+                let o = (row.binormal * angle.cos() + row.normal * angle.sin()) * 50.0 * scale;
+                lines.add(row.pos, row.pos + o, [0.0, 255.0, 0.0, 1.0]);
+            }
+        }
+
+        if let Some((offset, both)) = self.preview {
+
+            // Derive the would-be controls from moved copies of the end points
+            let (mut from, mut to) = (self.from.clone(), self.to.clone());
+            if both || !self.active_point {
+                from.pos = self.translated(from.pos, offset);
+            }
+            if both || self.active_point {
+                to.pos = self.translated(to.pos, offset);
+            }
+
+            let (b, c, _, _) = self.control_points_for(&from, &to);
+            let (pf, pt) = (from.pos, to.pos);
+
+            let end = if self.active_point {
+                pt
+
+            } else {
+                pf
+            };
+
+            lines.add(end, end + Vector3::new(0.0, 300.0 * scale, 0.0), [128.0, 128.0, 128.0, 1.0]);
+            lines.add(pf, b.pos, [255.0, 128.0, 0.0, 0.5]);
+            lines.add(pt, c.pos, [0.0, 128.0, 255.0, 0.5]);
+            lines.add(b.pos, b.pos + Vector3::new(0.0, 100.0 * scale, 0.0), [128.0, 128.0, 128.0, 1.0]);
+            lines.add(c.pos, c.pos + Vector3::new(0.0, 100.0 * scale, 0.0), [128.0, 128.0, 128.0, 1.0]);
+
         }
 
     }
@@ -183,15 +804,17 @@ impl Segment {
         self.angle = 0.0;
         self.from.roll = 0.0;
         self.to.roll = 0.0;
+        self.bank = 0.0;
+        self.mirrored = false;
         self.typ = SegmentType::Straight;
 
         if self.active_point {
             self.to.pos = origin;
-            self.from.pos = origin - Vector3::new(500.0, 0.0, 0.0);
+            self.from.pos = origin - Vector3::new(self.straight_length, 0.0, 0.0);
 
         } else {
             self.from.pos = origin;
-            self.to.pos = origin + Vector3::new(500.0, 0.0, 0.0);
+            self.to.pos = origin + Vector3::new(self.straight_length, 0.0, 0.0);
         }
     }
 
@@ -199,6 +822,8 @@ impl Segment {
         self.angle = 180.0;
         self.from.roll = 0.0;
         self.to.roll = 0.0;
+        self.bank = 0.0;
+        self.mirrored = false;
         self.typ = SegmentType::Curve180;
 
         if self.active_point {
@@ -215,6 +840,8 @@ impl Segment {
         self.angle = 180.0;
         self.from.roll = 0.0;
         self.to.roll = 0.0;
+        self.bank = 0.0;
+        self.mirrored = false;
         self.typ = SegmentType::Curve90;
 
         if self.active_point {
@@ -231,6 +858,8 @@ impl Segment {
         self.angle = 0.0;
         self.from.roll = 0.0;
         self.to.roll = 0.0;
+        self.bank = 0.0;
+        self.mirrored = false;
         self.typ = SegmentType::Looping;
 
         if self.active_point {
@@ -262,29 +891,79 @@ impl Segment {
 
     }
 
-    fn translate(&mut self, offset: Vector3<f32>, invert: bool) {
-        if self.active_point {
-            if invert {
-                self.from.pos += offset;
+    fn queue_rotation(&mut self, origin: Vector3<f32>, angle: f32) {
+        // Quickly queued rotations accumulate towards the final angle around
+        // the origin of the first one
+        if self.rotation_remaining == 0.0 {
+            self.rotation_origin = origin;
+        }
+        self.rotation_remaining += angle;
+    }
 
-            } else {
-                self.to.pos += offset;
-            }
+    fn animate_rotation(&mut self, dt: f32) {
+
+        if self.rotation_remaining == 0.0 {
+            return;
+        }
+
+        let step = (ROTATION_SPEED * dt).min(self.rotation_remaining.abs());
+        self.rotation_remaining -= step * self.rotation_remaining.signum();
+        if self.rotation_remaining.abs() < 0.01 {
+            self.rotation_remaining = 0.0;
+        }
+
+        // Display the not yet reached part of the rotation by rotating the
+        // already generated mesh back, this avoids regenerating it each frame
+        let r: Matrix4<f32> = Quaternion::from(Euler {
+            x: Deg(0.0),
+            y: Deg(self.rotation_remaining),
+            z: Deg(0.0)
+
+        }).into();
+
+        self.mesh.transform = Matrix4::from_translation(self.rotation_origin)
+            * r
+            * Matrix4::from_translation(-self.rotation_origin);
+
+    }
+
+    fn translation_axis(&self, forward: f32, right: f32) -> Vector3<f32> {
+        if self.local_translation {
+            // Same basis the straight control points are derived from
+            let a = PI / 180.0 * self.angle;
+            Vector3::new(a.cos(), 0.0, a.sin()) * forward + Vector3::new(-a.sin(), 0.0, a.cos()) * right
 
         } else {
-            if invert {
-                self.to.pos += offset;
+            Vector3::new(forward, 0.0, right)
+        }
+    }
 
-            } else {
-                self.from.pos += offset;
-            }
+    // Fine steps round to their own spacing so they are not undone by the
+    // grid
+    fn translate(&mut self, offset: Vector3<f32>, invert: bool) {
+        if self.active_point == invert {
+            self.from.pos = self.translated(self.from.pos, offset);
+
+        } else {
+            self.to.pos = self.translated(self.to.pos, offset);
+        }
+    }
+
+    // Position moved by the offset and snapped to the grid when enabled
+    fn translated(&self, pos: Vector3<f32>, offset: Vector3<f32>) -> Vector3<f32> {
+        let mut pos = pos + offset;
+        if self.snap_to_grid {
+            let snap = offset.magnitude().max(SNAP_MIN).min(self.snap);
+            pos.x = (pos.x / snap).round() * snap;
+            pos.z = (pos.z / snap).round() * snap;
         }
+        pos
     }
 
     // TODO two sided shader?
     fn generate(&mut self) {
 
-        let (rows, fa, ta) = match self.typ {
+        let (mut rows, fa, ta) = match self.typ {
             SegmentType::Looping => {
 
                 let dx = (self.from.pos.x - self.to.pos.x).abs();
@@ -300,9 +979,10 @@ impl Segment {
                     self.from.clone(),
                     self.to.clone(),
                     height,
-                    self.angle
+                    self.angle,
+                    self.mirrored
                 );
-                (looping.generate_segments(50.0), -self.angle, -self.angle)
+                (looping.generate_segments(self.row_step), -self.angle, -self.angle)
 
             },
             _ => {
@@ -310,36 +990,55 @@ impl Segment {
                 let a = self.from.clone();
                 let d = self.to.clone();
                 let bezier = Bezier::new(a, b, c, d);
-                (bezier.generate_segments(50.0), fa, ta)
+                (bezier.generate_segments(self.row_step), fa, ta)
             }
         };
 
 
-        let (v, i) = triangulate(&rows[..], 3, fa, ta);
-        self.mesh = Mesh::from_raw(v, i);
+        let last = (rows.len() as f32 - 1.0).max(1.0);
+        for (i, row) in rows.iter_mut().enumerate() {
+            let scale = self.width_profile.scale(i as f32 / last, self.from.width, self.to.width);
+            row.width = (row.width * scale).max(MIN_ROW_WIDTH);
+            row.roll += self.bank * (PI * i as f32 / last).sin();
+        }
+
+        let (v, t, i) = triangulate(&rows[..], self.columns(), fa, ta);
+        self.mesh.set_geometry(v, i);
+        self.mesh.set_tangents(t);
+        self.modified = true;
         self.mesh.set_color([1.0, 1.0, 0.0, 1.0]);
         self.rows = rows;
 
     }
 
     fn control_points(&self) -> (Point, Point, f32, f32) {
+        self.control_points_for(&self.from, &self.to)
+    }
+
+    // Controls the segment would have with its end points at from and to
+    fn control_points_for(&self, from: &Point, to: &Point) -> (Point, Point, f32, f32) {
         match self.typ {
             SegmentType::Curve180 => {
-                let v = self.to.pos - self.from.pos;
-                let u = Vector3::new(-v.z, 0.0, v.x);
-                let s = 2.0 / 3.0;
+                let v = to.pos - from.pos;
+                let u = if self.mirrored {
+                    Vector3::new(v.z, 0.0, -v.x)
 
-                let b = self.from.pos - u * s;
-                let c = self.to.pos - u * s;
+                } else {
+                    Vector3::new(-v.z, 0.0, v.x)
+                };
+                let s = 2.0 / 3.0 * self.tightness;
+
+                let b = from.pos - u * s;
+                let c = to.pos - u * s;
                 (
-                    Point::new(b.x, b.y, b.z, self.from.width, self.from.roll),
-                    Point::new(c.x, c.y, c.z, self.to.width, self.to.roll),
+                    Point::new(b.x, b.y, b.z, from.width, from.roll),
+                    Point::new(c.x, c.y, c.z, to.width, to.roll),
                     (self.angle + 180.0) % 360.0,
                     self.angle
                 )
             },
             SegmentType::Curve90 => {
-                let v = self.to.pos - self.from.pos;
+                let v = to.pos - from.pos;
                 let (u, w) = if self.angle == 0.0 || self.angle == 180.0 {
                     (Vector3::new(v.x, 0.0, 0.0), Vector3::new(0.0, 0.0, -v.z))
 
@@ -350,33 +1049,41 @@ impl Segment {
                     (Vector3::new(0.0, 0.0, v.z), Vector3::new(-v.x, 0.0, 0.0))
                 };
 
-                let s = 0.55228;
+                let s = 0.55228 * self.tightness;
+
+                // Mirrored curves turn the other way round
+                let turn = if self.mirrored {
+                    90.0
 
-                let b = self.from.pos + u * s;
-                let c = self.to.pos + w * s;
+                } else {
+                    270.0
+                };
+
+                let b = from.pos + u * s;
+                let c = to.pos + w * s;
                 (
-                    Point::new(b.x, b.y, b.z, self.from.width, self.from.roll),
-                    Point::new(c.x, c.y, c.z, self.to.width, self.to.roll),
+                    Point::new(b.x, b.y, b.z, from.width, from.roll),
+                    Point::new(c.x, c.y, c.z, to.width, to.roll),
                     self.angle + 180.0,
-                    (self.angle + 270.0) % 360.0
+                    (self.angle + turn) % 360.0
                 )
             },
             SegmentType::Straight => {
                 // TODO support adjusting controls points
-                let dx = (self.from.pos.x - self.to.pos.x).abs();
-                let dz = (self.from.pos.z - self.to.pos.z).abs();
+                let dx = (from.pos.x - to.pos.x).abs();
+                let dz = (from.pos.z - to.pos.z).abs();
                 let d = dx.max(dz) * 1.33;
                 (
-                    self.from.rotate_around(self.angle, d * 0.5),
-                    self.to.rotate_around(self.angle + 180.0, d * 0.5),
+                    from.rotate_around(self.angle, d * 0.5),
+                    to.rotate_around(self.angle + 180.0, d * 0.5),
                     self.angle,
                     self.angle
                 )
             },
             SegmentType::Looping => {
-                let d = self.to.pos - self.from.pos;
+                let d = to.pos - from.pos;
                 // TODO display second control point at lower edge of loop
-                (self.from.clone(), self.to.clone(), self.angle, self.angle)
+                (from.clone(), to.clone(), self.angle, self.angle)
             }
         }
     }
@@ -385,7 +1092,81 @@ impl Segment {
 
 
 // Helpers --------------------------------------------------------------------
-enum SegmentType {
+pub enum Axis {
+    X,
+    Z
+}
+
+// Shapes the track width along a segment on top of the end point widths
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum WidthProfile {
+    Linear,
+    EaseInOut,
+    Pinch,
+    Flare
+}
+
+impl WidthProfile {
+
+    pub fn next(&self) -> WidthProfile {
+        match *self {
+            WidthProfile::Linear => WidthProfile::EaseInOut,
+            WidthProfile::EaseInOut => WidthProfile::Pinch,
+            WidthProfile::Pinch => WidthProfile::Flare,
+            WidthProfile::Flare => WidthProfile::Linear
+        }
+    }
+
+    // Width multiplier at the normalized position t along the segment
+    fn scale(&self, t: f32, from: f32, to: f32) -> f32 {
+        match *self {
+            WidthProfile::Linear => 1.0,
+            WidthProfile::EaseInOut => {
+                // Replaces the linear blend between both end point widths
+                let linear = lerp(from, to, t);
+                if linear > 0.0 {
+                    lerp(from, to, ease_in_out(t)) / linear
+
+                } else {
+                    1.0
+                }
+            },
+            WidthProfile::Pinch => 1.0 - 0.5 * (t * PI).sin(),
+            WidthProfile::Flare => 1.0 + 0.5 * (t * 2.0 - 1.0).powi(2)
+        }
+    }
+
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SegmentStats {
+    pub rows: usize,
+    pub vertices: usize,
+    pub triangles: usize,
+    pub length: f32,
+    pub min_y: f32,
+    pub max_y: f32,
+    pub non_finite: bool
+}
+
+#[derive(Debug, Clone)]
+pub struct SegmentState {
+    typ: SegmentType,
+    angle: f32,
+    mirrored: bool,
+    from: Point,
+    to: Point,
+    columns: Option<u32>,
+    straight_length: f32,
+    tightness: f32,
+    width_profile: WidthProfile,
+    row_step: f32,
+    bank: f32,
+    tags: Vec<String>
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SegmentType {
     Straight,
     Curve90,
     Curve180,
@@ -398,9 +1179,10 @@ pub fn triangulate(
     fa: f32,
     ta: f32
 
-) -> (Vec<Vector3<f32>>, Vec<u32>) {
+) -> (Vec<Vector3<f32>>, Vec<Vector3<f32>>, Vec<u32>) {
 
     let mut vertices = Vec::with_capacity(rows.len() * cols as usize);
+    let mut tangents = Vec::with_capacity(rows.len() * cols as usize);
     let mut indices = Vec::new();
 
     let last = rows.len().saturating_sub(1);
@@ -420,9 +1202,27 @@ pub fn triangulate(
         }
 
         let mut o = (s.binormal * angle.cos() + normal * angle.sin()) * s.width;
+
+        // Tangent follows the track forward direction, orthogonalized against
+        // the cross track direction so the frame stays handed-consistent
+        let forward = if index < last {
+            rows[index + 1].pos - s.pos
+
+        } else if index > 0 {
+            s.pos - rows[index - 1].pos
+
+        } else {
+            Vector3::zero()
+        };
+
+        let across = if o.is_zero() { o } else { o.normalize() };
+        let tangent = forward - across * across.dot(forward);
+        let tangent = if tangent.is_zero() { tangent } else { tangent.normalize() };
+
         let step = o * (2.0 / cols as f32);
         for _ in 0..cols + 1 {
             vertices.push((s.pos + o)) ;
+            tangents.push(tangent);
             o -= step;
         }
 
@@ -446,8 +1246,203 @@ pub fn triangulate(
 
     }
 
-    (vertices, indices)
+    (vertices, tangents, indices)
 
 }
 
 
+// Tests ----------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+
+    use std::f32::consts::PI;
+    use cgmath::{Vector3, InnerSpace, Zero};
+    use ::core::{Point, Row};
+    use super::{triangulate, Axis, Segment, SegmentType, WidthProfile, TIGHTNESS_MIN, TIGHTNESS_MAX};
+
+    fn straight_rows(count: usize) -> Vec<Row> {
+        (0..count).map(|i| Row {
+            pos: Vector3::new(i as f32 * 100.0, 0.0, 0.0),
+            binormal: Vector3::new(0.0, 1.0, 0.0),
+            normal: Vector3::new(0.0, 0.0, -1.0),
+            width: 100.0,
+            roll: 0.0
+
+        }).collect()
+    }
+
+    // Half circle around (0, 0, 500) entering along +X and leaving along -X
+    fn curve_rows(count: usize) -> Vec<Row> {
+        (0..count).map(|i| {
+            let a = PI * i as f32 / (count - 1) as f32;
+            Row {
+                pos: Vector3::new(a.sin() * 500.0, 0.0, 500.0 - a.cos() * 500.0),
+                binormal: Vector3::new(0.0, 1.0, 0.0),
+                normal: Vector3::new(a.sin(), 0.0, -a.cos()),
+                width: 100.0,
+                roll: 0.0
+            }
+
+        }).collect()
+    }
+
+    // Sanity check for triangulate output, every row must produce cols + 1
+    // vertices, indices must stay in bounds and no triangle may collapse
+    fn check_triangulation(
+        rows: usize,
+        cols: u32,
+        vertices: &[Vector3<f32>],
+        indices: &[u32]
+
+    ) -> Result<(), String> {
+
+        let expected = rows * (cols as usize + 1);
+        if vertices.len() != expected {
+            return Err(format!("{} vertices, expected {}", vertices.len(), expected));
+        }
+
+        let expected = rows.saturating_sub(1) * cols as usize * 6;
+        if indices.len() != expected {
+            return Err(format!("{} indices, expected {}", indices.len(), expected));
+        }
+
+        for (t, tri) in indices.chunks(3).enumerate() {
+            if let Some(i) = tri.iter().find(|i| **i as usize >= vertices.len()) {
+                return Err(format!("triangle {} index {} out of bounds", t, i));
+            }
+
+            let (a, b, c) = (vertices[tri[0] as usize], vertices[tri[1] as usize], vertices[tri[2] as usize]);
+            if tri[0] == tri[1] || tri[1] == tri[2] || tri[0] == tri[2] || (b - a).cross(c - a).is_zero() {
+                return Err(format!("triangle {} is degenerate", t));
+            }
+        }
+
+        Ok(())
+
+    }
+
+    #[test]
+    fn test_triangulate_straight() {
+        let (v, t, i) = triangulate(&straight_rows(5), 4, 0.0, 0.0);
+        assert_eq!(v.len(), 25);
+        assert_eq!(t.len(), 25);
+        assert_eq!(i.len(), 96);
+        assert_eq!(&i[..6], &[0, 5, 1, 5, 6, 1]);
+        assert_eq!(check_triangulation(5, 4, &v, &i), Ok(()));
+
+        // Tangents point along the track
+        for tangent in &t {
+            assert!((tangent - Vector3::new(1.0, 0.0, 0.0)).magnitude() < 0.001);
+        }
+    }
+
+    #[test]
+    fn test_triangulate_curve_180() {
+        let (v, t, i) = triangulate(&curve_rows(9), 6, 0.0, 180.0);
+        assert_eq!(v.len(), 63);
+        assert_eq!(t.len(), 63);
+        assert_eq!(i.len(), 288);
+        assert_eq!(check_triangulation(9, 6, &v, &i), Ok(()));
+
+        // Both end rows span the full track width across the curve
+        assert!(((v[0] - v[6]).magnitude() - 200.0).abs() < 0.01);
+        assert!(((v[56] - v[62]).magnitude() - 200.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_triangulate_eight_columns() {
+        let (v, t, i) = triangulate(&straight_rows(4), 8, 0.0, 0.0);
+        assert_eq!(v.len(), 36);
+        assert_eq!(t.len(), 36);
+        assert_eq!(i.len(), 144);
+        assert_eq!(&i[i.len() - 6..], &[25, 34, 26, 34, 35, 26]);
+        assert_eq!(check_triangulation(4, 8, &v, &i), Ok(()));
+    }
+
+    #[test]
+    fn test_check_triangulation_errors() {
+        let (v, _, i) = triangulate(&straight_rows(2), 2, 0.0, 0.0);
+        assert!(check_triangulation(3, 2, &v, &i).is_err());
+        assert!(check_triangulation(2, 2, &v, &i[..6]).is_err());
+
+        let mut bounds = i.clone();
+        bounds[0] = 99;
+        assert!(check_triangulation(2, 2, &v, &bounds).is_err());
+
+        let mut collapsed = v.clone();
+        collapsed[1] = collapsed[0];
+        assert!(check_triangulation(2, 2, &collapsed, &i).is_err());
+    }
+
+    // Sign of the face normal y of all triangles, None if they disagree or a
+    // triangle stands upright
+    fn facing(segment: &Segment) -> Option<f32> {
+        let signs: Vec<f32> = segment.mesh().triangles().iter().map(|&(a, b, c)| {
+            (b - a).cross(c - a).y.signum()
+
+        }).collect();
+        if signs.iter().all(|s| *s == signs[0]) { Some(signs[0]) } else { None }
+    }
+
+    #[test]
+    fn test_mirror_and_reverse_keep_winding() {
+        for typ in &[SegmentType::Straight, SegmentType::Curve90, SegmentType::Curve180] {
+            let mut segment = Segment::with_type(Point::new(0.0, 0.0, 0.0, 200.0, 0.0), *typ, 0.0);
+            let expected = facing(&segment);
+            assert!(expected.is_some(), "{:?} has mixed winding", typ);
+
+            segment.mirror(Axis::X);
+            assert_eq!(facing(&segment), expected, "{:?} mirrored across X", typ);
+            segment.mirror(Axis::Z);
+            assert_eq!(facing(&segment), expected, "{:?} mirrored across Z", typ);
+            segment.reverse();
+            assert_eq!(facing(&segment), expected, "{:?} reversed", typ);
+            segment.mirror(Axis::X);
+            assert_eq!(facing(&segment), expected, "{:?} reversed and mirrored", typ);
+        }
+    }
+
+    #[test]
+    fn test_queued_rotations_keep_first_origin() {
+        let mut segment = Segment::new(Point::new(0.0, 0.0, 0.0, 200.0, 0.0), 0.0);
+
+        let (a, b) = (Vector3::new(0.0, 0.0, 0.0), Vector3::new(500.0, 0.0, 0.0));
+        segment.queue_rotation(a, 90.0);
+        segment.queue_rotation(b, 90.0);
+        assert_eq!(segment.rotation_origin, a);
+        assert_eq!(segment.rotation_remaining, 180.0);
+
+        segment.animate_rotation(1.0);
+        segment.queue_rotation(b, 90.0);
+        assert_eq!(segment.rotation_origin, b);
+    }
+
+    #[test]
+    fn test_tightness_keeps_end_points() {
+        let mut segment = Segment::with_type(Point::new(0.0, 0.0, 0.0, 200.0, 0.0), SegmentType::Curve90, 0.0);
+        let (from, to, length) = (segment.start_point(), segment.end_point(), segment.length());
+
+        segment.set_tightness(0.0);
+        assert_eq!(segment.tightness(), TIGHTNESS_MIN);
+        assert!(segment.length() < length);
+        assert_eq!((segment.start_point(), segment.end_point()), (from, to));
+
+        segment.set_tightness(10.0);
+        assert_eq!(segment.tightness(), TIGHTNESS_MAX);
+        assert!(segment.length() > length);
+    }
+
+    #[test]
+    fn test_width_profile_cycle() {
+        let mut profile = WidthProfile::Linear;
+        let mut seen = Vec::new();
+        for _ in 0..4 {
+            seen.push(profile);
+            profile = profile.next();
+        }
+        assert_eq!(profile, WidthProfile::Linear);
+        assert_eq!(seen, vec![WidthProfile::Linear, WidthProfile::EaseInOut, WidthProfile::Pinch, WidthProfile::Flare]);
+    }
+
+}
+
@@ -0,0 +1,71 @@
+// Copyright (c) 2017 Ivo Wetzel
+
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+// STD Dependencies -----------------------------------------------------------
+use std::fmt;
+use std::io;
+use std::error::Error;
+use std::path::PathBuf;
+
+
+// Asset Loading Errors -------------------------------------------------------
+#[derive(Debug)]
+pub enum AssetError {
+    // File could not be opened or read
+    Io(PathBuf, io::Error),
+    // Shader program failed to compile, link or create a pipeline
+    Shader(String, String),
+    // GPU side resource could not be created
+    Device(String)
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AssetError::Io(ref path, ref err) => write!(f, "Failed to read {}: {}", path.display(), err),
+            AssetError::Shader(ref name, ref log) => write!(f, "Shader {} failed: {}", name, log),
+            AssetError::Device(ref msg) => write!(f, "Failed to create GPU resource: {}", msg)
+        }
+    }
+}
+
+impl Error for AssetError {
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            AssetError::Io(_, ref err) => Some(err),
+            _ => None
+        }
+    }
+
+}
+
+
+
+// Tests ----------------------------------------------------------------------
+#[cfg(test)]
+mod tests {
+
+    use std::io;
+    use std::error::Error;
+    use std::path::PathBuf;
+    use super::AssetError;
+
+    #[test]
+    fn test_source_and_message() {
+        let err = AssetError::Io(PathBuf::from("mesh.vs"), io::Error::new(io::ErrorKind::NotFound, "missing"));
+        assert_eq!(err.to_string(), "Failed to read mesh.vs: missing");
+        assert_eq!(err.source().map(|e| e.to_string()), Some("missing".to_string()));
+
+        let err = AssetError::Device("out of memory".to_string());
+        assert_eq!(err.to_string(), "Failed to create GPU resource: out of memory");
+        assert!(err.source().is_none());
+    }
+
+}
@@ -22,17 +22,87 @@ extern crate image;
 
 
 // STD Dependencies -----------------------------------------------------------
+use std::env;
+use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
 
 
 // External Dependencies ------------------------------------------------------
-use renderer::{Key, Keyboard, Mouse, Renderable, RenderTarget, Encoder, Factory, ColorBuffer, DepthBuffer};
-use cgmath::{Vector3};
+use renderer::{Key, Keyboard, Button, Mouse, InputRecording, Renderable, RenderTarget, RunConfig, Encoder, Factory, ColorBuffer, DepthBuffer, FrameStats, Viewport};
+use cgmath::{InnerSpace, Matrix4, SquareMatrix, Vector3};
 
 mod core;
+mod error;
 mod render;
 
-use self::core::{Camera, Course, Glider, Mesh};
-use self::render::{LineView, MeshView};
+use self::core::{Camera, CameraMode, ContactRays, Course, EventLog, Glider, GliderAnimation, Mesh, Random, BINDINGS, ROW_STEP_MAX};
+use self::error::AssetError;
+use self::render::{LineDepth, LineView, MeshView, RenderStats, SkyView, TextView, Thumbnail, TrailView, WIREFRAME_OFFSET};
+
+
+// Statics --------------------------------------------------------------------
+const GENERATED_SEGMENTS: usize = 12;
+const MAX_SEGMENTS: usize = 128;
+const THUMBNAIL_SIZE: (u32, u32) = (320, 180);
+const THUMBNAIL_PATH: &'static str = "thumbnail.png";
+
+// Average fps below which the course tessellation is coarsened while playing
+const TARGET_FPS: u32 = 60;
+const LOW_FPS: f32 = 45.0;
+const LOW_FPS_DURATION: u64 = 3;
+
+// Lines available for debug drawing, grown up to the maximum for the
+// spatial index overlay
+const LINE_BUDGET: usize = 1000;
+const MAX_LINE_BUDGET: usize = 50000;
+
+// Range of the length multiplier for debug gizmos, changed in factors of 2
+const DEBUG_SCALE_MIN: f32 = 0.125;
+const DEBUG_SCALE_MAX: f32 = 16.0;
+
+// Space around the course and minimum height above the grid the free-fly
+// camera is kept within
+const CAMERA_MARGIN: f32 = 5000.0;
+const CAMERA_MIN_HEIGHT: f32 = 10.0;
+
+// Shown while the glider is beside the track
+const OFF_TRACK: &'static str = "OFF TRACK";
+
+// Multiplier applied to the glider's surface alignment rates per key press
+const ALIGN_RATE_FACTOR: f32 = 1.5;
+
+// Multiplier applied to the hover spring stiffness per key press, the
+// damping follows with its square root to keep the ride from bouncing
+const HOVER_STIFFNESS_FACTOR: f32 = 1.5;
+
+// Seconds below the course after which the glider respawns at the start
+const GLIDER_MAX_AIRTIME: f32 = 2.0;
+
+// Top speed in reverse, an eighth of the forward top speed
+const GLIDER_MAX_REVERSE_SPEED: f32 = 15.0;
+
+// (pull, release) rates per second of the chase camera around occluding
+// track, snaps in front of walls quickly and eases back out
+const CAMERA_PULL_RATES: (f32, f32) = (20.0, 3.0);
+
+// (length, width) of the glider footprint sampled by the corner contact rays
+const CONTACT_FOOTPRINT: (f32, f32) = (60.0, 30.0);
+
+// (name, ambient tint, sky top, sky bottom) presets cycled with F4
+const TIME_OF_DAY: [(&'static str, [f32; 3], [f32; 4], [f32; 4]); 3] = [
+    ("DAY", [1.0, 1.0, 1.0], [0.15, 0.3, 0.6, 1.0], [0.55, 0.7, 0.85, 1.0]),
+    ("DUSK", [0.85, 0.55, 0.45], [0.15, 0.1, 0.25, 1.0], [0.7, 0.3, 0.15, 1.0]),
+    ("NIGHT", [0.25, 0.3, 0.5], [0.0, 0.0, 0.02, 1.0], [0.02, 0.04, 0.1, 1.0])
+];
+
+// (name, color, polygon offset) wireframe presets cycled with Shift B, a zero
+// alpha keeps the vertex colors
+const WIREFRAME_STYLES: [(&'static str, [f32; 4], (i32, i32)); 3] = [
+    ("WIREFRAME TINTED", [0.0, 1.0, 0.5, 1.0], WIREFRAME_OFFSET),
+    ("WIREFRAME VERTEX COLORS", [0.0, 0.0, 0.0, 0.0], WIREFRAME_OFFSET),
+    ("WIREFRAME NO OFFSET", [0.0, 1.0, 0.5, 1.0], (0, 0))
+];
 
 
 // Game -----------------------------------------------------------------------
@@ -40,47 +110,254 @@ pub struct Game {
     factory: Factory,
     wireframe: bool,
     editing: bool,
+    show_debug: bool,
+    debug_scale: f32,
+    show_help: bool,
+    show_collision: bool,
+    time_scale: f32,
+    time_of_day: usize,
+    wireframe_style: usize,
+    camera_detached: bool,
+    corner_rays: bool,
+    animate_glider: bool,
+    camera_blend: f32,
+    orbiting: bool,
+    editor_view: Matrix4<f32>,
+    current_segment: Option<usize>,
+    course_bounds: Option<(Vector3<f32>, Vector3<f32>)>,
+    segment_entry: Option<String>,
+    warning: Option<(String, f32)>,
+    drag: Option<(i32, i32)>,
+    rng: Random,
+    split_screen: bool,
+    viewport: Viewport,
     camera: Camera,
+    preview_camera: Camera,
     course: Course,
     glider: Glider,
 
     editor_grid: Mesh,
+    grid_size: f32,
+    grid_cells: usize,
 
     line_view: LineView,
-    mesh_view: MeshView
+    mesh_view: MeshView,
+    sky_view: SkyView,
+    text_view: TextView,
+    trail_view: TrailView,
+    thumbnail: Option<Thumbnail>,
+
+    frame_stats: FrameStats,
+    render_stats: RenderStats
 }
 
 impl Game {
-    pub fn new(mut target: RenderTarget) -> Self {
+    pub fn new(mut target: RenderTarget) -> Result<Self, AssetError> {
 
         let line_view = LineView::new(
             &mut target.factory,
             target.color.clone(),
             target.depth.clone(),
-            500
-        );
+            LINE_BUDGET,
+            target.viewport
+        )?;
 
         let mesh_view = MeshView::new(
             &mut target.factory,
             target.color.clone(),
-            target.depth.clone()
-        );
+            target.depth.clone(),
+            target.viewport
+        )?;
+
+        let mut sky_view = SkyView::new(
+            &mut target.factory,
+            target.color.clone(),
+            target.viewport
+        )?;
+        sky_view.set_background_gradient(TIME_OF_DAY[0].2, TIME_OF_DAY[0].3);
+
+        let trail_view = TrailView::new(
+            &mut target.factory,
+            target.color.clone(),
+            target.depth.clone(),
+            120,
+            target.viewport
+        )?;
+
+        let text_view = TextView::new(
+            &mut target.factory,
+            target.color.clone(),
+            target.depth.clone(),
+            8192,
+            target.viewport
+        )?;
+
+        let mut course = Course::new();
+        course.set_max_segments(MAX_SEGMENTS);
+
+        let mut glider = Glider::new();
+        glider.set_max_airtime(GLIDER_MAX_AIRTIME);
+        glider.set_max_reverse_speed(GLIDER_MAX_REVERSE_SPEED);
+        glider.set_camera_pull_rates(CAMERA_PULL_RATES.0, CAMERA_PULL_RATES.1);
+        glider.respawn(&course);
 
-        Self {
+        Ok(Self {
             factory: target.factory,
             wireframe: false,
             editing: true,
+            show_debug: true,
+            debug_scale: 1.0,
+            show_collision: false,
+            show_help: false,
+            time_scale: 1.0,
+            time_of_day: 0,
+            wireframe_style: 0,
+            camera_detached: false,
+            corner_rays: false,
+            animate_glider: true,
+            camera_blend: 1.0,
+            orbiting: false,
+            editor_view: Matrix4::identity(),
+            current_segment: None,
+            course_bounds: None,
+            segment_entry: None,
+            warning: None,
+            drag: None,
+            rng: Random::new(rand::random()),
+            split_screen: false,
+            viewport: target.viewport,
             camera: Camera::new(target.width, target.height, 60.0),
-            course: Course::new(),
-            glider: Glider::new(),
+            preview_camera: Camera::new(target.width, target.height, 60.0),
+            course: course,
+            glider: glider,
 
             editor_grid: Mesh::from_grid_plane(10_000.0, 10_000.0, 100, 100),
+            grid_size: 10_000.0,
+            grid_cells: 100,
 
             line_view: line_view,
-            mesh_view: mesh_view
+            mesh_view: mesh_view,
+            sky_view: sky_view,
+            text_view: text_view,
+            trail_view: trail_view,
+            thumbnail: None,
+
+            frame_stats: FrameStats::default(),
+            render_stats: RenderStats::default()
+        })
+
+    }
+
+    // Starts on a course generated from the seed, the same seed always
+    // yields the same course
+    pub fn new_with_seed(target: RenderTarget, seed: u32) -> Result<Self, AssetError> {
+        let mut game = Game::new(target)?;
+        game.rng = Random::new(seed);
+        game.course.generate(&mut game.rng, GENERATED_SEGMENTS);
+        game.glider.respawn(&game.course);
+        println!("[Game] Generated course from seed {}", game.rng.seed());
+        Ok(game)
+    }
+
+    // Typing a segment number selects it, digit keys are swallowed while
+    // the entry is open so they neither edit the segment nor change time
+    fn enter_segment(&mut self, keyboard: &Keyboard) -> bool {
+
+        if keyboard.was_pressed(Key::Escape) {
+            self.segment_entry = if self.segment_entry.is_some() {
+                None
+
+            } else {
+                Some(String::new())
+            };
+            return true;
+        }
+
+        if let Some(mut entry) = self.segment_entry.take() {
+
+            let digits = vec![
+                Key::Key0, Key::Key1, Key::Key2, Key::Key3, Key::Key4,
+                Key::Key5, Key::Key6, Key::Key7, Key::Key8, Key::Key9
+            ];
+
+            for (i, key) in digits.into_iter().enumerate() {
+                if keyboard.was_pressed(key) && entry.len() < 4 {
+                    entry.push_str(&i.to_string());
+                }
+            }
+
+            if keyboard.was_pressed(Key::Return) {
+                // Numbers past the end select the last segment
+                if let Ok(index) = entry.parse() {
+                    self.course.set_active(index);
+                    self.warning = Some((format!("SEGMENT {}", self.course.active_index()), 2.0));
+                }
+
+            } else {
+                self.segment_entry = Some(entry);
+            }
+
+            true
+
+        } else {
+            false
+        }
+
+    }
+
+    // Play mode always starts from a fresh glider at the course start behind
+    // the chase camera, the free-fly editor camera is restored on return
+    fn toggle_editing(&mut self) {
+
+        self.editing = !self.editing;
+        self.segment_entry = None;
+        self.drag = None;
+        self.camera_detached = false;
+        self.camera_blend = 1.0;
+        self.current_segment = None;
+
+        if self.editing {
+            self.camera.set_view(self.editor_view);
+
+        } else {
+            self.editor_view = self.camera.view();
+            self.glider.respawn(&self.course);
+            self.glider.snap_camera();
+            self.trail_view.clear();
         }
 
     }
+
+    // Both cameras render into their half of the window when split, each
+    // with its own aspect ratio
+    fn update_cameras(&mut self) {
+        if self.split_screen {
+            let (main, preview) = self.viewport.split();
+            self.camera.set_viewport(main);
+            self.preview_camera.set_viewport(preview);
+
+        } else {
+            self.camera.set_viewport(self.viewport);
+        }
+    }
+
+    fn set_viewport(&mut self, viewport: Viewport) {
+        self.mesh_view.set_viewport(viewport);
+        self.sky_view.set_viewport(viewport);
+        self.line_view.set_viewport(viewport);
+        self.trail_view.set_viewport(viewport);
+    }
+
+    fn set_grid(&mut self, size: f32, cells: usize) {
+        self.grid_size = size.max(1_000.0).min(80_000.0);
+        self.grid_cells = cells.max(10).min(400);
+
+        // Replacing the mesh drops its vertex buffer handle which releases
+        // the GPU side buffer on the next device cleanup
+        self.editor_grid = Mesh::from_grid_plane(self.grid_size, self.grid_size, self.grid_cells, self.grid_cells);
+        self.course.set_snap(self.grid_size / self.grid_cells as f32);
+    }
+
 }
 
 impl Renderable for Game {
@@ -91,68 +368,501 @@ impl Renderable for Game {
         dt: f32,
         mut encoder: &mut Encoder,
         keyboard: &Keyboard,
-        _: &Mouse,
-        resized: Option<((u32, u32), ColorBuffer, DepthBuffer)>
+        mouse: &Mouse,
+        resized: Option<(Viewport, ColorBuffer, DepthBuffer)>
 
     ) where Self: Sized {
 
+        // Reset counters up front so a frame without draws reports zero
+        self.mesh_view.reset_stats();
+        self.sky_view.reset_stats();
+        self.line_view.reset_stats();
+        self.text_view.reset_stats();
+        self.trail_view.reset_stats();
+
         if let Some(resized) = resized {
 
             self.mesh_view.resize(resized.clone());
+            self.sky_view.resize(resized.clone());
             self.line_view.resize(resized.clone());
+            self.text_view.resize(resized.clone());
+            self.trail_view.resize(resized.clone());
 
-            let size = resized.0;
-            self.camera.resize(size.0, size.1);
+            self.viewport = resized.0;
+            self.update_cameras();
 
         }
 
-        if keyboard.was_pressed(Key::B) {
+        if keyboard.was_pressed(Key::B) && keyboard.is_pressed(Key::LShift) {
+            self.wireframe_style = (self.wireframe_style + 1) % WIREFRAME_STYLES.len();
+            let (name, color, (factor, units)) = WIREFRAME_STYLES[self.wireframe_style];
+            self.mesh_view.set_wireframe_color(color);
+            self.mesh_view.set_wireframe_offset(&mut self.factory, factor, units);
+            self.warning = Some((name.to_string(), 1.0));
+
+        } else if keyboard.was_pressed(Key::B) {
             self.wireframe = !self.wireframe;
             self.mesh_view.reload(&mut self.factory, self.wireframe);
             self.line_view.reload(&mut self.factory, self.wireframe);
+            self.trail_view.reload(&mut self.factory, self.wireframe);
         }
 
         if keyboard.was_pressed(Key::R) {
             self.mesh_view.reload(&mut self.factory, self.wireframe);
+            self.sky_view.reload(&mut self.factory);
             self.line_view.reload(&mut self.factory, self.wireframe);
-            self.glider.set_position(self.course.start_point() + Vector3::new(10.0, 25.0, 0.0));
+            self.trail_view.reload(&mut self.factory, self.wireframe);
+            self.trail_view.clear();
+            self.glider.respawn(&self.course);
+        }
+
+        // The thumbnail rendered on the previous frame has been flushed by now
+        let factory = &mut self.factory;
+        if let Some(image) = self.thumbnail.as_mut().and_then(|t| t.take_image(factory)) {
+            match image.save(THUMBNAIL_PATH) {
+                Ok(_) => println!("[Thumbnail] Saved {}", THUMBNAIL_PATH),
+                Err(err) => println!("[Thumbnail] Failed to save {}: {}", THUMBNAIL_PATH, err)
+            }
+        }
+
+        if keyboard.was_pressed(Key::P) && keyboard.is_pressed(Key::LShift) {
+            if self.thumbnail.is_none() {
+                match Thumbnail::new(&mut self.factory, THUMBNAIL_SIZE.0, THUMBNAIL_SIZE.1) {
+                    Ok(thumbnail) => self.thumbnail = Some(thumbnail),
+                    Err(err) => println!("[Thumbnail] {}", err)
+                }
+            }
+            if let Some(ref mut thumbnail) = self.thumbnail {
+                thumbnail.render(encoder, &mut self.factory, &mut self.course);
+            }
+
+        } else if keyboard.was_pressed(Key::P) {
+            println!(
+                "[Stats] {:.1} fps ({:.1} average), {:.2}ms work, {} draw calls, {} triangles, {} lines",
+                self.frame_stats.fps(),
+                self.frame_stats.average_fps,
+                self.frame_stats.work_time * 1000.0,
+                self.render_stats.draw_calls,
+                self.render_stats.triangles,
+                self.render_stats.lines
+            );
+
+            // Measured a few frames earlier, not part of the work time above
+            if let Some(gpu_time) = self.frame_stats.lagging_gpu_time {
+                println!("[Stats] {:.2}ms gpu (lagging)", gpu_time * 1000.0);
+            }
+        }
+
+        // Alt leaves + - to the tessellation
+        if keyboard.is_pressed(Key::LAlt) && (keyboard.was_pressed(Key::Plus) || keyboard.was_pressed(Key::Minus)) {
+            let scale = if keyboard.was_pressed(Key::Plus) { self.debug_scale * 2.0 } else { self.debug_scale * 0.5 };
+            self.debug_scale = scale.max(DEBUG_SCALE_MIN).min(DEBUG_SCALE_MAX);
+            self.warning = Some((format!("DEBUG SCALE {}", self.debug_scale), 1.0));
+        }
+
+        if keyboard.was_pressed(Key::F4) {
+            self.time_of_day = (self.time_of_day + 1) % TIME_OF_DAY.len();
+            self.mesh_view.set_ambient(TIME_OF_DAY[self.time_of_day].1);
+            self.sky_view.set_background_gradient(TIME_OF_DAY[self.time_of_day].2, TIME_OF_DAY[self.time_of_day].3);
+            self.warning = Some((TIME_OF_DAY[self.time_of_day].0.to_string(), 1.0));
+        }
+
+        // Play preview through the glider camera next to the main view
+        if keyboard.was_pressed(Key::F6) {
+            self.split_screen = !self.split_screen;
+            self.update_cameras();
+        }
+
+        if keyboard.was_pressed(Key::F1) {
+            self.show_help = !self.show_help;
         }
 
         if keyboard.was_pressed(Key::Tab) {
-            self.editing = !self.editing;
+            self.toggle_editing();
         }
 
-        let view = if self.editing {
+        let entering = self.editing && self.enter_segment(&keyboard);
 
-            // X-Axis
-            self.line_view.add(Vector3::new(-5.0, 0.0, -5.0), Vector3::new(1000.0, 0.0, -5.0), [255.0, 0.0, 0.0, 1.0]);
+        // Toggle grid, axes and debug overlays for clean screenshots
+        if keyboard.was_pressed(Key::H) {
+            if keyboard.is_pressed(Key::LShift) {
+                self.show_collision = !self.show_collision;
 
-            // Y-Axis
-            self.line_view.add(Vector3::new(-5.0, 0.0, -5.0), Vector3::new(-5.0, 1000.0, -5.0), [0.0, 255.0, 0.0, 1.0]);
+            } else {
+                self.show_debug = !self.show_debug;
+            }
+        }
+
+        if !entering {
+
+            // Slow motion / fast forward
+            if keyboard.was_pressed(Key::Key5) {
+                self.time_scale = 0.25;
+
+            } else if keyboard.was_pressed(Key::Key6) {
+                self.time_scale = 1.0;
 
-            // Z-Axis
-            self.line_view.add(Vector3::new(-5.0, 0.0, -5.0), Vector3::new(-5.0, 0.0, 1000.0), [0.0, 0.0, 255.0, 1.0]);
+            } else if keyboard.was_pressed(Key::Key7) {
+                self.time_scale = 2.0;
+            }
+
+            // Pause the glider, also while inspecting it from the editor
+            if keyboard.was_pressed(Key::Key0) {
+                let paused = !self.glider.is_paused();
+                self.glider.set_paused(paused);
+            }
+
+        }
 
-            self.camera.update(&keyboard);
-            self.course.edit(&keyboard);
-            self.course.debug(&mut self.line_view);
+        // The raw frame time is scaled only for simulation, a scale of zero
+        // freezes the glider entirely while rendering continues
+        let sim_dt = dt * self.time_scale;
+
+        let view = if self.editing {
+
+            if self.show_debug {
+
+                // X-Axis
+                self.line_view.add(Vector3::new(-5.0, 0.0, -5.0), Vector3::new(1000.0, 0.0, -5.0), [255.0, 0.0, 0.0, 1.0]);
+
+                // Y-Axis
+                self.line_view.add(Vector3::new(-5.0, 0.0, -5.0), Vector3::new(-5.0, 1000.0, -5.0), [0.0, 255.0, 0.0, 1.0]);
+
+                // Z-Axis
+                self.line_view.add(Vector3::new(-5.0, 0.0, -5.0), Vector3::new(-5.0, 0.0, 1000.0), [0.0, 0.0, 255.0, 1.0]);
+
+            }
+
+            // Grid extent, with shift the number of subdivisions, Ctrl+Z
+            // restores deleted segments instead
+            let shift = keyboard.is_pressed(Key::LShift);
+            if keyboard.was_pressed(Key::Z) && !keyboard.is_pressed(Key::LControl) {
+                if shift {
+                    self.set_grid(self.grid_size, self.grid_cells / 2);
+
+                } else {
+                    self.set_grid(self.grid_size * 0.5, self.grid_cells);
+                }
+
+            } else if keyboard.was_pressed(Key::X) {
+                if shift {
+                    self.set_grid(self.grid_size, self.grid_cells * 2);
+
+                } else {
+                    self.set_grid(self.grid_size * 2.0, self.grid_cells);
+                }
+            }
+
+            // Orbit the edited segment with mouse drag and wheel
+            if !entering && keyboard.was_pressed(Key::Return) {
+                self.orbiting = !self.orbiting;
+            }
+
+            if self.orbiting {
+                let (x, y) = mouse.position();
+                let (dx, dy) = match self.drag {
+                    Some((px, py)) if mouse.is_pressed(Button::Left) => (x - px, y - py),
+                    _ => (0, 0)
+                };
+                self.drag = if mouse.is_pressed(Button::Left) {
+                    Some((x, y))
+
+                } else {
+                    None
+                };
+                self.camera.orbit(self.course.focus_point(), dx as f32 * 0.5, dy as f32 * 0.5, mouse.wheel() * 100.0);
+
+            } else {
+                self.camera.update(&keyboard);
+                if keyboard.was_pressed(Key::C) && keyboard.is_pressed(Key::LShift) {
+                    let (center, radius) = self.course.bounding_sphere();
+                    self.camera.frame(center, radius);
+                }
+
+                // Select the segment under the cursor
+                if mouse.was_pressed(Button::Left) {
+                    let (x, y) = mouse.position();
+                    if let Some(index) = self.camera.screen_ray(x, y, self.viewport.window).and_then(|ray| self.course.pick(ray)) {
+                        self.course.set_active(index);
+                    }
+                }
+            }
+            if !entering {
+                self.course.edit(&keyboard, dt);
+                if self.course.take_refused() {
+                    self.warning = Some(("SEGMENT LIMIT REACHED".to_string(), 2.0));
+                }
+                if keyboard.was_pressed(Key::Y) {
+                    let text = if self.course.snaps_to_grid() {
+                        format!("SNAP TO {} GRID", self.grid_size / self.grid_cells as f32)
+
+                    } else {
+                        "SNAP OFF".to_string()
+                    };
+                    self.warning = Some((text, 2.0));
+                }
+                if !keyboard.is_pressed(Key::LAlt) && (keyboard.was_pressed(Key::Plus) || keyboard.was_pressed(Key::Minus)) {
+                    let text = format!("ROW STEP {} {} TRIANGLES", self.course.row_step(), self.course.triangle_count());
+                    self.warning = Some((text, 2.0));
+                }
+            }
+
+            // Tracks dipping below the ground would z-fight with the grid,
+            // move the grid beneath them and tighten the depth range
+            let (min, max) = self.course.bounds();
+            if min.y < 0.0 {
+                self.editor_grid.transform = Matrix4::from_translation(Vector3::new(0.0, min.y - 50.0, 0.0));
+                self.camera.set_clip_range(1.0, ((max - min).magnitude() * 2.0).max(15000.0));
+
+            } else {
+                self.editor_grid.transform = Matrix4::from_translation(Vector3::new(0.0, 0.0, 0.0));
+                self.camera.set_clip_range(0.01, 15000.0);
+            }
+
+            // Keep the free-fly camera around the course and above the grid
+            if self.course_bounds != Some((min, max)) {
+                self.course_bounds = Some((min, max));
+                let floor = self.editor_grid.transform.w.y + CAMERA_MIN_HEIGHT;
+                let margin = Vector3::new(CAMERA_MARGIN, CAMERA_MARGIN, CAMERA_MARGIN);
+                self.camera.set_bounds(Some((
+                    Vector3::new(min.x - CAMERA_MARGIN, floor, min.z - CAMERA_MARGIN),
+                    max + margin
+                )));
+            }
+            if self.show_debug {
+                let view_projection = self.camera.projection() * self.camera.view();
+                self.course.debug(&mut self.line_view, &mut self.text_view, view_projection, self.debug_scale);
+            }
             self.camera.view()
 
         } else {
-            self.glider.update(dt, &self.course, &mut self.line_view, &keyboard);
-            self.glider.camera_view()
+            // Rewind while held, the trail no longer matches the path
+            if keyboard.was_pressed(Key::Y) {
+                self.trail_view.clear();
+            }
+
+            if keyboard.is_pressed(Key::Y) {
+                self.glider.rewind(1);
+
+            } else if sim_dt > 0.0 {
+                self.glider.update(sim_dt, &self.course, &keyboard);
+                self.line_view.add_many(self.glider.contact_lines());
+                if !self.glider.is_paused() {
+                    self.trail_view.push(self.glider.position(), self.glider.up());
+                }
+            }
+            // Never hides other warnings, only keeps refreshing itself
+            let shows_other = match self.warning {
+                Some((ref text, _)) => text != OFF_TRACK,
+                None => false
+            };
+            if !shows_other && self.course.is_off_track(self.glider.position()) {
+                self.warning = Some((OFF_TRACK.to_string(), 0.25));
+            }
+
+            // Announce the tags of each segment the glider enters, jumps
+            // within the same segment do not count as entering it again
+            if let Some(segment) = self.glider.current_segment() {
+                if Some(segment) != self.current_segment {
+                    self.current_segment = Some(segment);
+                    let tags = self.course.tags(segment);
+                    if !tags.is_empty() {
+                        self.warning = Some((tags.join(" ").to_uppercase(), 2.0));
+                    }
+                }
+            }
+            if !self.show_debug {
+                // Discard the collision ray overlays
+                self.line_view.clear();
+            }
+
+            // Cycle chase camera presets
+            if keyboard.was_pressed(Key::V) {
+                let mode = self.glider.camera_mode().next();
+                self.glider.set_camera_mode(mode);
+            }
+
+            // Tune how quickly the glider follows the surface, with Shift how
+            // floaty the hover feels
+            if keyboard.is_pressed(Key::LShift) && (keyboard.was_pressed(Key::N) || keyboard.was_pressed(Key::M)) {
+                let factor = if keyboard.was_pressed(Key::M) { HOVER_STIFFNESS_FACTOR } else { 1.0 / HOVER_STIFFNESS_FACTOR };
+                let (stiffness, damping) = self.glider.hover_response();
+                self.glider.set_hover_response(stiffness * factor, damping * factor.sqrt());
+
+                let (stiffness, damping) = self.glider.hover_response();
+                self.warning = Some((format!("HOVER RESPONSE {:.0} / {:.1}", stiffness, damping), 2.0));
+
+            } else if keyboard.was_pressed(Key::N) || keyboard.was_pressed(Key::M) {
+                let factor = if keyboard.was_pressed(Key::M) { ALIGN_RATE_FACTOR } else { 1.0 / ALIGN_RATE_FACTOR };
+                let (ground, air) = self.glider.align_rate();
+                self.glider.set_align_rate(ground * factor, air * factor);
+
+                let (ground, air) = self.glider.align_rate();
+                self.warning = Some((format!("ALIGN RATE {:.1} / {:.1}", ground, air), 2.0));
+            }
+
+            if keyboard.was_pressed(Key::T) {
+                let model = self.glider.turn_model().next();
+                self.warning = Some((format!("TURN MODEL {}", model.name()), 2.0));
+                self.glider.set_turn_model(model);
+            }
+
+            // Lock the model to the physics transform to inspect the actual
+            // orientation
+            if keyboard.was_pressed(Key::K) {
+                self.animate_glider = !self.animate_glider;
+                if self.animate_glider {
+                    self.glider.set_animation(GliderAnimation::default());
+                    self.warning = Some(("MODEL ANIMATION ON".to_string(), 2.0));
+
+                } else {
+                    self.glider.set_animation(GliderAnimation::none());
+                    self.warning = Some(("MODEL ANIMATION OFF".to_string(), 2.0));
+                }
+            }
+
+            // Switch between the default rays and sampling all four corners
+            if keyboard.was_pressed(Key::G) {
+                self.corner_rays = !self.corner_rays;
+                if self.corner_rays {
+                    self.glider.set_contact_rays(ContactRays::corners(CONTACT_FOOTPRINT.0, CONTACT_FOOTPRINT.1));
+                    self.warning = Some(("CORNER CONTACT RAYS".to_string(), 2.0));
+
+                } else {
+                    self.glider.set_contact_rays(ContactRays::default());
+                    self.warning = Some(("DEFAULT CONTACT RAYS".to_string(), 2.0));
+                }
+            }
+            self.glider.update_camera(dt, &self.course);
+
+            // Detach the camera and leave it where it is, re-attaching blends
+            // back towards the glider view
+            if keyboard.was_pressed(Key::F) {
+                self.camera_detached = !self.camera_detached;
+                if self.camera_detached {
+                    self.camera.set_view(self.glider.camera_view());
+
+                } else {
+                    self.camera_blend = 0.0;
+                }
+            }
+
+            if self.camera_detached {
+                self.camera.update(&keyboard);
+                self.camera.view()
+
+            } else if self.camera_blend < 1.0 {
+                self.camera_blend = (self.camera_blend + dt * 2.0).min(1.0);
+                self.camera.follow(self.glider.camera_view(), self.camera_blend);
+                self.camera.view()
+
+            } else {
+                self.glider.camera_view()
+            }
         };
 
-        self.glider.debug(&mut self.line_view);
-
-        // Draw everything else
-        self.mesh_view.draw(encoder, &mut self.factory, &self.camera, view, &mut self.editor_grid);
+        if self.show_debug {
+            self.glider.debug(&mut self.line_view, self.debug_scale);
+        }
+        if self.show_collision {
+            let lines = (LINE_BUDGET + self.course.debug_tree_lines()).min(MAX_LINE_BUDGET);
+            self.line_view.reserve(&mut self.factory, lines);
+
+            // Occluded by the track but leaves the depth buffer to it
+            let depth = self.line_view.depth();
+            self.line_view.set_depth(LineDepth::Test);
+            self.course.debug_tree(&mut self.line_view);
+            self.line_view.set_depth(depth);
+        }
+        self.course.markers(&mut self.line_view);
+
+        // Draw everything else, the sky covers each viewport so the letterbox
+        // bars keep the black of the frame clear
+        let main = if self.split_screen { self.viewport.split().0 } else { self.viewport };
+        self.set_viewport(main);
+        self.sky_view.draw(encoder);
+        if self.show_debug {
+            self.mesh_view.draw(encoder, &mut self.factory, &self.camera, view, &mut self.editor_grid);
+        }
         for mut m in self.course.meshes() {
             self.mesh_view.draw(encoder, &mut self.factory, &self.camera, view, &mut m);
         }
-        self.mesh_view.draw(encoder, &mut self.factory, &self.camera, view, &mut self.glider.mesh);
+        if self.show_collision {
+            let mesh = self.course.collision_debug_mesh();
+            if mesh.triangle_count() > 0 {
+                self.mesh_view.draw(encoder, &mut self.factory, &self.camera, view, mesh);
+            }
+        }
+
+        // The glider would cover the whole view from its own cockpit
+        let first_person = !self.editing
+            && !self.camera_detached
+            && self.glider.camera_mode() == CameraMode::FirstPerson;
+
+        if !first_person {
+            self.mesh_view.draw(encoder, &mut self.factory, &self.camera, view, &mut self.glider.mesh);
+        }
         self.line_view.draw(encoder, &self.camera, view);
+        self.trail_view.draw(encoder, &self.camera, view);
+
+        // The preview shares the course geometry, debug lines are consumed by
+        // the main view and only show up there
+        if self.split_screen {
+            let preview = self.viewport.split().1;
+            let view = self.glider.camera_view();
+            self.set_viewport(preview);
+            self.sky_view.draw(encoder);
+            for mut m in self.course.meshes() {
+                self.mesh_view.draw(encoder, &mut self.factory, &self.preview_camera, view, &mut m);
+            }
+            if self.glider.camera_mode() != CameraMode::FirstPerson {
+                self.mesh_view.draw(encoder, &mut self.factory, &self.preview_camera, view, &mut self.glider.mesh);
+            }
+            self.trail_view.draw(encoder, &self.preview_camera, view);
+        }
+
+        // Overlays
+        if let Some((text, remaining)) = self.warning.take() {
+            self.text_view.add(10.0, 40.0, 12.0, &text, [255.0, 64.0, 0.0, 1.0]);
+            if remaining > dt {
+                self.warning = Some((text, remaining - dt));
+            }
+        }
+        if let Some(ref entry) = self.segment_entry {
+            let text = format!("SEGMENT {}_ OF {}", entry, self.course.segment_count());
+            self.text_view.add(10.0, 10.0, 12.0, &text, [255.0, 200.0, 0.0, 1.0]);
+        }
+        if self.show_help {
+            for (i, b) in BINDINGS.iter().enumerate() {
+                let y = 10.0 + i as f32 * 18.0;
+                self.text_view.add(10.0, y, 10.0, b.context, [255.0, 200.0, 0.0, 1.0]);
+                self.text_view.add(90.0, y, 10.0, b.keys, [255.0, 255.0, 255.0, 1.0]);
+                self.text_view.add(200.0, y, 10.0, b.description, [192.0, 192.0, 192.0, 1.0]);
+            }
+        }
+        self.text_view.draw(encoder);
+
+        self.render_stats = self.mesh_view.stats()
+            + self.sky_view.stats()
+            + self.line_view.stats()
+            + self.trail_view.stats()
+            + self.text_view.stats();
+
+    }
+
+    fn frame_stats(&mut self, stats: &FrameStats) {
+        self.frame_stats = *stats;
+    }
 
+    // The glider physics degrade at low frame rates, trade detail for speed
+    // unless the tessellation is being edited by hand
+    fn low_fps(&mut self, fps: f32) {
+        let step = self.course.row_step();
+        if !self.editing && step < ROW_STEP_MAX {
+            self.course.regenerate_all(step * 2.0);
+            let text = format!("LOW FPS {:.0}, ROW STEP {}", fps, self.course.row_step());
+            self.warning = Some((text, 2.0));
+        }
     }
 
 }
@@ -160,8 +870,48 @@ impl Renderable for Game {
 
 // Main -----------------------------------------------------------------------
 pub fn main() {
-    renderer::run::<Game, _>("Glider", 800, 600, 60, move |refs| {
-        Game::new(refs)
+
+    // --record <file> logs all input, --playback <file> replays it,
+    // --smooth-dt evens out frame time jitter at the cost of hiding hitches,
+    // --seed <n> starts on a course generated from the seed,
+    // --log-events prints glider landings and takeoffs
+    let args: Vec<String> = env::args().collect();
+    let mut recording = InputRecording::Off;
+    let mut dt_smoothing = None;
+    let mut seed = None;
+    let mut log_events = false;
+    for (i, arg) in args.iter().enumerate().skip(1) {
+        match (arg.as_str(), args.get(i + 1)) {
+            ("--record", Some(path)) => recording = InputRecording::Record(PathBuf::from(path)),
+            ("--playback", Some(path)) => recording = InputRecording::Playback(PathBuf::from(path)),
+            ("--smooth-dt", _) => dt_smoothing = Some(0.1),
+            ("--seed", Some(value)) => seed = value.parse().ok(),
+            ("--log-events", _) => log_events = true,
+            _ => {}
+        }
+    }
+
+    let config = RunConfig {
+        title: "Glider".to_string(),
+        fps: TARGET_FPS,
+        dt_smoothing: dt_smoothing,
+        low_fps: Some((LOW_FPS, Duration::from_secs(LOW_FPS_DURATION))),
+        recording: recording,
+        ..RunConfig::default()
+    };
+    renderer::run::<Game, _>(config, move |refs| {
+        let game = match seed {
+            Some(seed) => Game::new_with_seed(refs, seed),
+            None => Game::new(refs)
+        };
+        let mut game = game.unwrap_or_else(|err| {
+            println!("[Game] {}", err);
+            process::exit(1);
+        });
+        if log_events {
+            game.glider.set_events(Some(Box::new(EventLog::default())));
+        }
+        game
     });
 }
 
@@ -15,16 +15,28 @@ use gfx::state::Rasterizer;
 use gfx_device_gl;
 use std::fs::File;
 use std::path::PathBuf;
-use std::io::{self, Read};
-use std::error::Error;
+use std::io::Read;
 
 // External Dependencies ------------------------------------------------------
 use cgmath::{Matrix4, SquareMatrix, Vector3};
-use renderer::{ColorBuffer, DepthBuffer};
+use renderer::{ColorBuffer, DepthBuffer, Viewport};
 
 
 // Internal Dependencies ------------------------------------------------------
+use ::error::AssetError;
 use ::core::Camera;
+use ::render::RenderStats;
+
+
+// Depth Behaviour ------------------------------------------------------------
+// Selected per line, both kinds are drawn with their own pipeline
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LineDepth {
+    // Tested against and written to the depth buffer
+    Write,
+    // Occluded by geometry but leaves the depth buffer untouched
+    Test
+}
 
 
 // 3D Lines Rendering Implementation -----------------------------------------
@@ -32,11 +44,17 @@ use ::core::Camera;
 pub struct LineView {
     vertices: Vec<Vertex>,
     pso: gfx::PipelineState<gfx_device_gl::Resources, line::Meta>,
+    test_pso: gfx::PipelineState<gfx_device_gl::Resources, line::Meta>,
+    depth: LineDepth,
     data: line::Data<gfx_device_gl::Resources>,
     slice: gfx::Slice<gfx_device_gl::Resources>,
     dirty: bool,
+    // Depth writing lines fill the vertices from the front, depth testing
+    // ones from the back
     lines: usize,
-    max_lines: usize
+    test_lines: usize,
+    max_lines: usize,
+    stats: RenderStats
 }
 
 impl LineView {
@@ -45,9 +63,10 @@ impl LineView {
         factory: &mut gfx_device_gl::Factory,
         color: ColorBuffer,
         depth: DepthBuffer,
-        max_lines: usize
+        max_lines: usize,
+        viewport: Viewport
 
-    ) -> Self {
+    ) -> Result<Self, AssetError> {
 
         let mut vertices = Vec::with_capacity(max_lines);
         for _ in 0..max_lines {
@@ -64,9 +83,11 @@ impl LineView {
 
         ).expect("QuadView: Could not create `vertex_buffer`");
 
-        Self {
+        Ok(Self {
             vertices: vertices,
-            pso: LineView::create_pipeline(factory, false).unwrap(),
+            pso: LineView::create_pipeline(factory, LineDepth::Write)?,
+            test_pso: LineView::create_pipeline(factory, LineDepth::Test)?,
+            depth: LineDepth::Write,
             data: line::Data {
                 buf: vertex_buffer,
                 transform: factory.create_constant_buffer(1),
@@ -74,7 +95,8 @@ impl LineView {
                 view: Matrix4::identity().into(),
                 proj: Matrix4::identity().into(),
                 out_color: color,
-                out_depth: depth
+                out_depth: depth,
+                scissor: viewport.rect()
             },
             slice: gfx::Slice {
                 instances: None,
@@ -85,32 +107,106 @@ impl LineView {
             },
             dirty: true,
             lines: 0,
-            max_lines: max_lines
-        }
+            test_lines: 0,
+            max_lines: max_lines,
+            stats: RenderStats::default()
+        })
     }
 
     pub fn add(&mut self, from: Vector3<f32>, to: Vector3<f32>, color: [f32; 4]) {
-        if self.lines < self.max_lines {
-            let color = gamma_srgb_to_linear(color);
-            self.vertices[self.lines * 2].pos = from.into();
-            self.vertices[self.lines * 2].color = color;
-            self.vertices[self.lines * 2 + 1].pos = to.into();
-            self.vertices[self.lines * 2 + 1].color = color;
-            self.lines += 1;
+        if self.push(from, to, color) {
             self.dirty = true;
         }
     }
 
-    pub fn reload(&mut self, factory: &mut gfx_device_gl::Factory, wireframe: bool) {
-        match LineView::create_pipeline(factory, wireframe) {
-            Ok(pso) => self.pso = pso,
-            Err(err) => println!("{:?}", err)
+    pub fn add_many(&mut self, lines: &[(Vector3<f32>, Vector3<f32>, [f32; 4])]) {
+        let mut added = false;
+        for &(from, to, color) in lines {
+            added |= self.push(from, to, color);
+        }
+        self.dirty |= added;
+    }
+
+    // Connects consecutive points into a polyline
+    pub fn add_strip(&mut self, points: &[Vector3<f32>], color: [f32; 4]) {
+        let mut added = false;
+        for p in points.windows(2) {
+            added |= self.push(p[0], p[1], color);
+        }
+        self.dirty |= added;
+    }
+
+    pub fn stats(&self) -> RenderStats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats.reset();
+    }
+
+    pub fn clear(&mut self) {
+        self.lines = 0;
+        self.test_lines = 0;
+    }
+
+    // Lines that can still be added before the next draw
+    pub fn remaining(&self) -> usize {
+        self.max_lines - self.lines - self.test_lines
+    }
+
+    // Grows the line budget, lines added so far are kept
+    pub fn reserve(&mut self, factory: &mut gfx_device_gl::Factory, max_lines: usize) {
+
+        if max_lines <= self.max_lines {
+            return;
+        }
+
+        // Depth testing lines stay at the back
+        let old = self.max_lines * 2;
+        self.vertices.resize(max_lines * 2, Vertex { pos: [0.0, 0.0, 0.0], color: [0.0, 0.0, 0.0, 1.0] });
+        let tail = self.test_lines * 2;
+        for i in 0..tail {
+            self.vertices[max_lines * 2 - 1 - i] = self.vertices[old - 1 - i];
+        }
+        self.data.buf = factory.create_buffer::<Vertex>(
+            self.vertices.len() * 2,
+            gfx::buffer::Role::Vertex,
+            gfx::memory::Usage::Dynamic,
+            gfx::Bind::empty()
+
+        ).expect("LineView: Could not grow `vertex_buffer`");
+        self.max_lines = max_lines;
+        self.dirty = true;
+
+    }
+
+    pub fn reload(&mut self, factory: &mut gfx_device_gl::Factory, _: bool) {
+        match (LineView::create_pipeline(factory, LineDepth::Write), LineView::create_pipeline(factory, LineDepth::Test)) {
+            (Ok(pso), Ok(test_pso)) => {
+                self.pso = pso;
+                self.test_pso = test_pso;
+            },
+            (Err(err), _) | (_, Err(err)) => println!("[LineView] {}", err)
         }
     }
 
-    pub fn resize(&mut self, screen: ((u32, u32), ColorBuffer, DepthBuffer)) {
+    pub fn depth(&self) -> LineDepth {
+        self.depth
+    }
+
+    // Applies to the lines added afterwards
+    pub fn set_depth(&mut self, depth: LineDepth) {
+        self.depth = depth;
+    }
+
+    pub fn resize(&mut self, screen: (Viewport, ColorBuffer, DepthBuffer)) {
         self.data.out_color = screen.1;
         self.data.out_depth = screen.2;
+        self.data.scissor = screen.0.rect();
+    }
+
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.data.scissor = viewport.rect();
     }
 
     pub fn draw(
@@ -119,29 +215,77 @@ impl LineView {
         camera: &Camera,
         view: Matrix4<f32>
     ) {
+        self.draw_projected(encoder, camera.projection(), view);
+    }
+
+    pub fn draw_projected(
+        &mut self,
+        encoder: &mut gfx::Encoder<gfx_device_gl::Resources, gfx_device_gl::CommandBuffer>,
+        projection: Matrix4<f32>,
+        view: Matrix4<f32>
+    ) {
 
         self.data.view = view.into();
 
         let transform = Transform {
             model: self.data.model,
             view: self.data.view,
-            proj: camera.projection().into(),
+            proj: projection.into(),
         };
 
         if self.dirty {
             self.dirty = false;
-            encoder.update_buffer(&self.data.buf, &self.vertices, 0).ok();
+            if let Err(err) = encoder.update_buffer(&self.data.buf, &self.vertices, 0) {
+                println!("[LineView] {}", err);
+            }
         }
 
+        encoder.update_buffer(&self.data.transform, &[transform], 0).unwrap();
+
+        self.slice.start = 0;
         self.slice.end = (self.lines as u32) * 2;
+        self.stats.draw_calls += 1;
+        self.stats.lines += self.lines;
+        encoder.draw(&self.slice, &self.pso, &self.data);
+
+        if self.test_lines > 0 {
+            self.slice.start = ((self.max_lines - self.test_lines) as u32) * 2;
+            self.slice.end = (self.max_lines as u32) * 2;
+            self.stats.draw_calls += 1;
+            self.stats.lines += self.test_lines;
+            encoder.draw(&self.slice, &self.test_pso, &self.data);
+        }
+
         self.lines = 0;
+        self.test_lines = 0;
 
-        encoder.update_buffer(&self.data.transform, &[transform], 0).unwrap();
-        encoder.draw(&self.slice, &self.pso, &self.data);
+    }
 
+    fn push(&mut self, from: Vector3<f32>, to: Vector3<f32>, color: [f32; 4]) -> bool {
+        if self.lines + self.test_lines < self.max_lines {
+            let index = match self.depth {
+                LineDepth::Write => {
+                    self.lines += 1;
+                    self.lines - 1
+                },
+                LineDepth::Test => {
+                    self.test_lines += 1;
+                    self.max_lines - self.test_lines
+                }
+            };
+            let color = gamma_srgb_to_linear(color);
+            self.vertices[index * 2].pos = from.into();
+            self.vertices[index * 2].color = color;
+            self.vertices[index * 2 + 1].pos = to.into();
+            self.vertices[index * 2 + 1].color = color;
+            true
+
+        } else {
+            false
+        }
     }
 
-    fn create_pipeline(factory: &mut gfx_device_gl::Factory, _: bool) -> Result<gfx::PipelineState<gfx_device_gl::Resources, line::Meta>, Box<Error>> {
+    fn create_pipeline(factory: &mut gfx_device_gl::Factory, depth: LineDepth) -> Result<gfx::PipelineState<gfx_device_gl::Resources, line::Meta>, AssetError> {
 
         let vertex = load_shader("lines.vs")?;
         let fragment = load_shader("lines.fs")?;
@@ -149,7 +293,8 @@ impl LineView {
         let shader_program = factory.link_program(
             &vertex[..],
             &fragment[..]
-        )?;
+
+        ).map_err(|e| AssetError::Shader("lines.vs / lines.fs".to_string(), e.to_string()))?;
 
         let mut r = Rasterizer::new_fill();
         r.method = gfx::state::RasterMethod::Line(2);
@@ -159,9 +304,15 @@ impl LineView {
             &shader_program,
             gfx::Primitive::LineList,
             r,
-            line::new()
-
-        ).unwrap())
+            line::Init {
+                out_depth: match depth {
+                    LineDepth::Write => gfx::preset::depth::LESS_EQUAL_WRITE,
+                    LineDepth::Test => gfx::preset::depth::LESS_EQUAL_TEST
+                },
+                .. line::new()
+            }
+
+        ).map_err(|e| AssetError::Shader("lines.vs / lines.fs".to_string(), e.to_string()))?)
     }
 
 }
@@ -208,19 +359,21 @@ gfx_defines!{
         proj: gfx::Global<[[f32; 4]; 4]> = "u_Proj",
         out_color: gfx::RenderTarget<gfx::format::Srgba8> = "Target0",
         out_depth: gfx::DepthTarget<gfx::format::DepthStencil> = gfx::preset::depth::LESS_EQUAL_WRITE,
+        scissor: gfx::Scissor = (),
     }
 }
 
 
-fn load_shader(filename: &str) -> Result<Vec<u8>, io::Error> {
+fn load_shader(filename: &str) -> Result<Vec<u8>, AssetError> {
 
     let mut path = PathBuf::new();
     path.push("../assets/shaders/");
     path.push(filename);
 
-    let mut file = File::open(&path)?;
     let mut code = Vec::new();
-    file.read_to_end(&mut code)?;
-    Ok(code)
+    match File::open(&path).and_then(|mut file| file.read_to_end(&mut code)) {
+        Ok(_) => Ok(code),
+        Err(err) => Err(AssetError::Io(path, err))
+    }
 }
 
@@ -14,17 +14,25 @@ use gfx::state::Rasterizer;
 use gfx_device_gl;
 use std::fs::File;
 use std::path::PathBuf;
-use std::io::{self, Read};
-use std::error::Error;
+use std::io::Read;
 
 
 // External Dependencies ------------------------------------------------------
 use cgmath::Matrix4;
-use renderer::{ColorBuffer, DepthBuffer};
+use renderer::{ColorBuffer, DepthBuffer, Viewport};
 
 
 // Internal Dependencies ------------------------------------------------------
+use ::error::AssetError;
 use ::core::{Camera, Mesh};
+use ::render::RenderStats;
+
+
+// Statics --------------------------------------------------------------------
+
+// Polygon offset (factor, units) of the wireframe pipeline, pulls the edges
+// towards the camera so coplanar wireframes stop shimmering
+pub const WIREFRAME_OFFSET: (i32, i32) = (-1, -1);
 
 
 // 3D Mesh Rendering Implementation -------------------------------------------
@@ -33,7 +41,13 @@ pub struct MeshView {
     pso: gfx::PipelineState<gfx_device_gl::Resources, mesh::Meta>,
     locals: gfx::handle::Buffer<gfx_device_gl::Resources, Locals>,
     out_color: ColorBuffer,
-    out_depth: DepthBuffer
+    out_depth: DepthBuffer,
+    scissor: gfx::Rect,
+    wireframe: bool,
+    wireframe_color: [f32; 4],
+    wireframe_offset: (i32, i32),
+    ambient: [f32; 3],
+    stats: RenderStats
 }
 
 impl MeshView {
@@ -41,18 +55,36 @@ impl MeshView {
     pub fn new(
         factory: &mut gfx_device_gl::Factory,
         color: ColorBuffer,
-        depth: DepthBuffer
+        depth: DepthBuffer,
+        viewport: Viewport
 
-    ) -> Self {
-        Self {
-            pso: MeshView::create_pipeline(factory, true).unwrap(),
+    ) -> Result<Self, AssetError> {
+        Ok(Self {
+            pso: MeshView::create_pipeline(factory, true)?,
             locals: factory.create_constant_buffer(1),
             out_color: color,
-            out_depth: depth
-        }
+            out_depth: depth,
+            scissor: viewport.rect(),
+            wireframe: true,
+            wireframe_color: [0.0, 1.0, 0.5, 1.0],
+            wireframe_offset: WIREFRAME_OFFSET,
+            ambient: [1.0; 3],
+            stats: RenderStats::default()
+        })
     }
 
-    pub fn create_pipeline(factory: &mut gfx_device_gl::Factory, wireframe: bool) -> Result<gfx::PipelineState<gfx_device_gl::Resources, mesh::Meta>, Box<Error>> {
+    pub fn create_pipeline(factory: &mut gfx_device_gl::Factory, wireframe: bool) -> Result<gfx::PipelineState<gfx_device_gl::Resources, mesh::Meta>, AssetError> {
+        MeshView::create_pipeline_with_offset(factory, wireframe, WIREFRAME_OFFSET)
+    }
+
+    // The offset only applies to the wireframe pipeline, filled meshes keep
+    // their exact depth
+    pub fn create_pipeline_with_offset(
+        factory: &mut gfx_device_gl::Factory,
+        wireframe: bool,
+        offset: (i32, i32)
+
+    ) -> Result<gfx::PipelineState<gfx_device_gl::Resources, mesh::Meta>, AssetError> {
 
         let vertex = load_shader("mesh.vs")?;
         let fragment = load_shader("mesh.fs")?;
@@ -60,11 +92,13 @@ impl MeshView {
         let shader_program = factory.link_program(
             &vertex[..],
             &fragment[..]
-        )?;
+
+        ).map_err(|e| AssetError::Shader("mesh.vs / mesh.fs".to_string(), e.to_string()))?;
 
         let mut r = Rasterizer::new_fill();
         if wireframe {
             r.method = gfx::state::RasterMethod::Line(1);
+            r.offset = Some(gfx::state::Offset(offset.0, offset.1));
         }
         r.samples = None;
 
@@ -74,19 +108,67 @@ impl MeshView {
             r,
             mesh::new()
 
-        ).unwrap())
+        ).map_err(|e| AssetError::Shader("mesh.vs / mesh.fs".to_string(), e.to_string()))?)
     }
 
     pub fn reload(&mut self, factory: &mut gfx_device_gl::Factory, wireframe: bool) {
-        match MeshView::create_pipeline(factory, wireframe) {
-            Ok(pso) => self.pso = pso,
-            Err(err) => println!("{:?}", err)
+        match MeshView::create_pipeline_with_offset(factory, wireframe, self.wireframe_offset) {
+            Ok(pso) => {
+                self.pso = pso;
+                self.wireframe = wireframe;
+            },
+            Err(err) => println!("[MeshView] {}", err)
         }
     }
 
-    pub fn resize(&mut self, screen: ((u32, u32), ColorBuffer, DepthBuffer)) {
+    // Polygon offset factor and units for the wireframe pipeline, rebuilds it
+    // right away while wireframe is active
+    pub fn set_wireframe_offset(&mut self, factory: &mut gfx_device_gl::Factory, factor: i32, units: i32) {
+        self.wireframe_offset = (factor, units);
+        if self.wireframe {
+            self.reload(factory, true);
+        }
+    }
+
+    // Replaces the vertex colors while the wireframe pipeline is active, the
+    // alpha component controls how much of the vertex color is replaced
+    pub fn set_wireframe_color(&mut self, color: [f32; 4]) {
+        self.wireframe_color = color;
+    }
+
+    // Tints the shading, ambient plus the fixed sun diffuse term is clamped
+    // to 1 so white ambient shows the plain vertex colors
+    pub fn set_ambient(&mut self, ambient: [f32; 3]) {
+        self.ambient = ambient;
+    }
+
+    // Fills the whole color target regardless of the viewport, only for
+    // targets without letterbox bars like the thumbnail
+    pub fn clear(&self, encoder: &mut gfx::Encoder<gfx_device_gl::Resources, gfx_device_gl::CommandBuffer>, color: [f32; 4]) {
+        encoder.clear(&self.out_color, color);
+    }
+
+    pub fn clear_depth(&self, encoder: &mut gfx::Encoder<gfx_device_gl::Resources, gfx_device_gl::CommandBuffer>) {
+        encoder.clear_depth(&self.out_depth, 1.0);
+    }
+
+    pub fn stats(&self) -> RenderStats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats.reset();
+    }
+
+    pub fn resize(&mut self, screen: (Viewport, ColorBuffer, DepthBuffer)) {
         self.out_color = screen.1;
         self.out_depth = screen.2;
+        self.scissor = screen.0.rect();
+    }
+
+    // Limits drawing to part of the window, e.g. one half of a split screen
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.scissor = viewport.rect();
     }
 
     pub fn draw(
@@ -97,15 +179,33 @@ impl MeshView {
         view: Matrix4<f32>,
         mesh: &mut Mesh
     ) {
+        self.draw_projected(encoder, factory, camera.projection(), view, mesh);
+    }
+
+    pub fn draw_projected(
+        &mut self,
+        encoder: &mut gfx::Encoder<gfx_device_gl::Resources, gfx_device_gl::CommandBuffer>,
+        factory: &mut gfx_device_gl::Factory,
+        projection: Matrix4<f32>,
+        view: Matrix4<f32>,
+        mesh: &mut Mesh
+    ) {
 
         if !mesh.is_rendered() {
-            mesh.render(factory)
+            mesh.render(factory, encoder)
         }
 
         let locals = Locals {
             model: mesh.transform.into(),
             view: view.into(),
-            proj: camera.projection().into(),
+            proj: projection.into(),
+            wireframe_color: if self.wireframe {
+                self.wireframe_color
+
+            } else {
+                [0.0; 4]
+            },
+            ambient: self.ambient
         };
 
         encoder.update_buffer(&self.locals, &[locals], 0).unwrap();
@@ -116,10 +216,13 @@ impl MeshView {
             blend_target: self.out_color.clone(),
             blend_ref: [1.0; 4],
             out_color: self.out_color.clone(),
-            out_depth: self.out_depth.clone()
+            out_depth: self.out_depth.clone(),
+            scissor: self.scissor
         };
 
         encoder.draw(mesh.slice.as_ref().unwrap(), &self.pso, &data);
+        self.stats.draw_calls += 1;
+        self.stats.triangles += mesh.triangle_count();
 
     }
 
@@ -130,6 +233,8 @@ impl MeshView {
 gfx_defines!{
     vertex Vertex {
         pos: [f32; 3] = "a_Pos",
+        normal: [f32; 3] = "a_Normal",
+        tangent: [f32; 3] = "a_Tangent",
         color: [f32; 4] = "a_Color",
     }
 
@@ -137,6 +242,8 @@ gfx_defines!{
         model: [[f32; 4]; 4] = "u_Model",
         view: [[f32; 4]; 4] = "u_View",
         proj: [[f32; 4]; 4] = "u_Proj",
+        wireframe_color: [f32; 4] = "u_WireframeColor",
+        ambient: [f32; 3] = "u_Ambient",
     }
 
     pipeline mesh {
@@ -146,21 +253,25 @@ gfx_defines!{
         blend_ref: gfx::BlendRef = (),
         out_color: gfx::RenderTarget<gfx::format::Srgba8> = "Target0",
         out_depth: gfx::DepthTarget<gfx::format::DepthStencil> = gfx::preset::depth::LESS_EQUAL_WRITE,
+        scissor: gfx::Scissor = (),
     }
 }
 
 pub use self::Vertex as MeshVertex;
+pub use self::Locals as MeshLocals;
+pub use self::mesh::{Data as MeshData, Meta as MeshMeta};
 
 
-fn load_shader(filename: &str) -> Result<Vec<u8>, io::Error> {
+fn load_shader(filename: &str) -> Result<Vec<u8>, AssetError> {
 
     let mut path = PathBuf::new();
     path.push("../assets/shaders/");
     path.push(filename);
 
-    let mut file = File::open(&path)?;
     let mut code = Vec::new();
-    file.read_to_end(&mut code)?;
-    Ok(code)
+    match File::open(&path).and_then(|mut file| file.read_to_end(&mut code)) {
+        Ok(_) => Ok(code),
+        Err(err) => Err(AssetError::Io(path, err))
+    }
 }
 
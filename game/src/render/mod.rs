@@ -1,9 +1,19 @@
 // Modules --------------------------------------------------------------------
 mod line;
 mod mesh;
+mod sky;
+mod stats;
+mod text;
+mod thumbnail;
+mod trail;
 
 
 // Re-Exports -----------------------------------------------------------------
-pub use self::line::LineView;
-pub use self::mesh::{MeshView, MeshVertex};
+pub use self::line::{LineDepth, LineView};
+pub use self::mesh::{MeshView, MeshVertex, MeshLocals, MeshData, MeshMeta, WIREFRAME_OFFSET};
+pub use self::sky::SkyView;
+pub use self::stats::RenderStats;
+pub use self::text::TextView;
+pub use self::thumbnail::Thumbnail;
+pub use self::trail::TrailView;
 
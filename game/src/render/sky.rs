@@ -0,0 +1,178 @@
+// Copyright (c) 2017 Ivo Wetzel
+
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+// External Dependencies ------------------------------------------------------
+use gfx;
+use gfx::traits::FactoryExt;
+use gfx::state::Rasterizer;
+use gfx_device_gl;
+use std::fs::File;
+use std::path::PathBuf;
+use std::io::Read;
+
+use renderer::{ColorBuffer, DepthBuffer, Viewport};
+
+
+// Internal Dependencies ------------------------------------------------------
+use ::error::AssetError;
+use ::render::RenderStats;
+
+
+// Sky Gradient Rendering Implementation --------------------------------------
+
+// Fullscreen quad drawn first each frame, the pipeline has no depth target so
+// it neither tests against nor writes depth and always ends up behind the
+// scene. The quad is in clip space and therefore fills the viewport at any
+// window size, resizing only swaps the targets and the scissor.
+pub struct SkyView {
+    top: [f32; 4],
+    bottom: [f32; 4],
+    pso: gfx::PipelineState<gfx_device_gl::Resources, sky::Meta>,
+    data: sky::Data<gfx_device_gl::Resources>,
+    slice: gfx::Slice<gfx_device_gl::Resources>,
+    stats: RenderStats
+}
+
+impl SkyView {
+
+    pub fn new(
+        factory: &mut gfx_device_gl::Factory,
+        color: ColorBuffer,
+        viewport: Viewport
+
+    ) -> Result<Self, AssetError> {
+
+        let quad = [
+            Vertex { pos: [-1.0, -1.0] }, Vertex { pos: [1.0, -1.0] }, Vertex { pos: [1.0, 1.0] },
+            Vertex { pos: [-1.0, -1.0] }, Vertex { pos: [1.0, 1.0] }, Vertex { pos: [-1.0, 1.0] }
+        ];
+        let (vbuf, slice) = factory.create_vertex_buffer_with_slice(&quad, ());
+
+        Ok(Self {
+            top: [0.1, 0.25, 0.6, 1.0],
+            bottom: [0.6, 0.75, 0.9, 1.0],
+            pso: SkyView::create_pipeline(factory)?,
+            data: sky::Data {
+                vbuf: vbuf,
+                locals: factory.create_constant_buffer(1),
+                out_color: color,
+                scissor: viewport.rect()
+            },
+            slice: slice,
+            stats: RenderStats::default()
+        })
+
+    }
+
+    // Colors at the top and bottom edge of the viewport
+    pub fn set_background_gradient(&mut self, top: [f32; 4], bottom: [f32; 4]) {
+        self.top = top;
+        self.bottom = bottom;
+    }
+
+    pub fn stats(&self) -> RenderStats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats.reset();
+    }
+
+    // The sky is never drawn as a wireframe, this only picks up shader changes
+    pub fn reload(&mut self, factory: &mut gfx_device_gl::Factory) {
+        match SkyView::create_pipeline(factory) {
+            Ok(pso) => self.pso = pso,
+            Err(err) => println!("[SkyView] {}", err)
+        }
+    }
+
+    pub fn resize(&mut self, screen: (Viewport, ColorBuffer, DepthBuffer)) {
+        self.data.out_color = screen.1;
+        self.data.scissor = screen.0.rect();
+    }
+
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.data.scissor = viewport.rect();
+    }
+
+    // Must be drawn first, directly after clearing the targets
+    pub fn draw(&mut self, encoder: &mut gfx::Encoder<gfx_device_gl::Resources, gfx_device_gl::CommandBuffer>) {
+
+        let locals = Locals {
+            top: self.top,
+            bottom: self.bottom
+        };
+
+        encoder.update_buffer(&self.data.locals, &[locals], 0).unwrap();
+        encoder.draw(&self.slice, &self.pso, &self.data);
+
+        self.stats.draw_calls += 1;
+        self.stats.triangles += 2;
+
+    }
+
+    fn create_pipeline(factory: &mut gfx_device_gl::Factory) -> Result<gfx::PipelineState<gfx_device_gl::Resources, sky::Meta>, AssetError> {
+
+        let vertex = load_shader("sky.vs")?;
+        let fragment = load_shader("sky.fs")?;
+
+        let shader_program = factory.link_program(
+            &vertex[..],
+            &fragment[..]
+
+        ).map_err(|e| AssetError::Shader("sky.vs / sky.fs".to_string(), e.to_string()))?;
+
+        let mut r = Rasterizer::new_fill();
+        r.samples = None;
+
+        Ok(factory.create_pipeline_from_program(
+            &shader_program,
+            gfx::Primitive::TriangleList,
+            r,
+            sky::new()
+
+        ).map_err(|e| AssetError::Shader("sky.vs / sky.fs".to_string(), e.to_string()))?)
+    }
+
+}
+
+
+// Data -----------------------------------------------------------------------
+gfx_defines!{
+    vertex Vertex {
+        pos: [f32; 2] = "a_Pos",
+    }
+
+    constant Locals {
+        top: [f32; 4] = "u_Top",
+        bottom: [f32; 4] = "u_Bottom",
+    }
+
+    pipeline sky {
+        vbuf: gfx::VertexBuffer<Vertex> = (),
+        locals: gfx::ConstantBuffer<Locals> = "Gradient",
+        out_color: gfx::RenderTarget<gfx::format::Srgba8> = "Target0",
+        scissor: gfx::Scissor = (),
+    }
+}
+
+
+fn load_shader(filename: &str) -> Result<Vec<u8>, AssetError> {
+
+    let mut path = PathBuf::new();
+    path.push("../assets/shaders/");
+    path.push(filename);
+
+    let mut code = Vec::new();
+    match File::open(&path).and_then(|mut file| file.read_to_end(&mut code)) {
+        Ok(_) => Ok(code),
+        Err(err) => Err(AssetError::Io(path, err))
+    }
+}
+
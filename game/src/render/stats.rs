@@ -0,0 +1,43 @@
+// Copyright (c) 2017 Ivo Wetzel
+
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+// STD Dependencies -----------------------------------------------------------
+use std::ops::Add;
+
+
+// Per Frame Rendering Statistics ---------------------------------------------
+#[derive(Debug, Default, Copy, Clone)]
+pub struct RenderStats {
+    pub draw_calls: usize,
+    pub triangles: usize,
+    pub lines: usize
+}
+
+impl RenderStats {
+
+    pub fn reset(&mut self) {
+        *self = RenderStats::default();
+    }
+
+}
+
+impl Add for RenderStats {
+
+    type Output = RenderStats;
+
+    fn add(self, other: RenderStats) -> RenderStats {
+        RenderStats {
+            draw_calls: self.draw_calls + other.draw_calls,
+            triangles: self.triangles + other.triangles,
+            lines: self.lines + other.lines
+        }
+    }
+
+}
+
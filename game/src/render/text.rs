@@ -0,0 +1,209 @@
+// Copyright (c) 2017 Ivo Wetzel
+
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+// External Dependencies ------------------------------------------------------
+use gfx;
+use gfx_device_gl;
+
+use cgmath::{self, Matrix4, SquareMatrix, Vector3, Vector4};
+use renderer::{ColorBuffer, DepthBuffer, Viewport};
+
+
+// Internal Dependencies ------------------------------------------------------
+use ::error::AssetError;
+use ::render::{LineView, RenderStats};
+
+
+// Screen Space Text Rendering Implementation ---------------------------------
+pub struct TextView {
+    lines: LineView,
+    depth: DepthBuffer,
+    size: (u32, u32)
+}
+
+impl TextView {
+
+    pub fn new(
+        factory: &mut gfx_device_gl::Factory,
+        color: ColorBuffer,
+        depth: DepthBuffer,
+        max_lines: usize,
+        viewport: Viewport
+
+    ) -> Result<Self, AssetError> {
+        Ok(Self {
+            lines: LineView::new(factory, color, depth.clone(), max_lines, viewport.full())?,
+            depth: depth,
+            size: viewport.window
+        })
+    }
+
+    // Adds text with its top left corner at the given pixel position, glyphs
+    // are drawn as segment display style line strokes
+    pub fn add(&mut self, x: f32, y: f32, size: f32, text: &str, color: [f32; 4]) {
+
+        let (w, h) = (size * 0.5, size);
+        let mut cx = x;
+        let mut cy = y;
+        for c in text.chars() {
+
+            if c == '\n' {
+                cx = x;
+                cy += h * 1.5;
+                continue;
+            }
+
+            let mask = glyph(c);
+            for (i, &(a, b)) in SEGMENTS.iter().enumerate() {
+                if mask & (1 << i) != 0 {
+                    self.lines.add(
+                        Vector3::new(cx + a.0 * w, cy + (1.0 - a.1 * 0.5) * h, 0.0),
+                        Vector3::new(cx + b.0 * w, cy + (1.0 - b.1 * 0.5) * h, 0.0),
+                        color
+                    );
+                }
+            }
+
+            cx += w * 1.6;
+
+        }
+
+    }
+
+    // Adds text anchored at a world space position, view_projection maps the
+    // position into clip space and text behind the camera is skipped
+    pub fn add_projected(&mut self, position: Vector3<f32>, view_projection: Matrix4<f32>, size: f32, text: &str, color: [f32; 4]) {
+        let p = view_projection * Vector4::new(position.x, position.y, position.z, 1.0);
+        if p.w > 0.0 {
+            let x = (p.x / p.w * 0.5 + 0.5) * self.size.0 as f32;
+            let y = (0.5 - p.y / p.w * 0.5) * self.size.1 as f32;
+            self.add(x, y, size, text, color);
+        }
+    }
+
+    pub fn stats(&self) -> RenderStats {
+        self.lines.stats()
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.lines.reset_stats();
+    }
+
+    pub fn resize(&mut self, screen: (Viewport, ColorBuffer, DepthBuffer)) {
+        // The overlay spans the whole window including any letterbox bars
+        self.size = screen.0.window;
+        self.depth = screen.2.clone();
+        self.lines.resize((screen.0.full(), screen.1, screen.2));
+    }
+
+    pub fn draw(
+        &mut self,
+        encoder: &mut gfx::Encoder<gfx_device_gl::Resources, gfx_device_gl::CommandBuffer>
+    ) {
+
+        // Overlay is always drawn on top of the scene
+        encoder.clear_depth(&self.depth, 1.0);
+
+        let projection = cgmath::ortho(0.0, self.size.0 as f32, self.size.1 as f32, 0.0, -1.0, 1.0);
+        self.lines.draw_projected(encoder, projection, Matrix4::identity());
+
+    }
+
+}
+
+
+// Glyphs ---------------------------------------------------------------------
+const A1: u16 = 1 << 0;
+const A2: u16 = 1 << 1;
+const B: u16 = 1 << 2;
+const C: u16 = 1 << 3;
+const D2: u16 = 1 << 4;
+const D1: u16 = 1 << 5;
+const E: u16 = 1 << 6;
+const F: u16 = 1 << 7;
+const G1: u16 = 1 << 8;
+const G2: u16 = 1 << 9;
+const H: u16 = 1 << 10;
+const I: u16 = 1 << 11;
+const J: u16 = 1 << 12;
+const K: u16 = 1 << 13;
+const L: u16 = 1 << 14;
+const M: u16 = 1 << 15;
+
+// Sixteen segment layout in a 1 x 2 cell with y pointing up
+const SEGMENTS: [((f32, f32), (f32, f32)); 16] = [
+    ((0.0, 2.0), (0.5, 2.0)), // A1
+    ((0.5, 2.0), (1.0, 2.0)), // A2
+    ((1.0, 2.0), (1.0, 1.0)), // B
+    ((1.0, 1.0), (1.0, 0.0)), // C
+    ((1.0, 0.0), (0.5, 0.0)), // D2
+    ((0.5, 0.0), (0.0, 0.0)), // D1
+    ((0.0, 0.0), (0.0, 1.0)), // E
+    ((0.0, 1.0), (0.0, 2.0)), // F
+    ((0.0, 1.0), (0.5, 1.0)), // G1
+    ((0.5, 1.0), (1.0, 1.0)), // G2
+    ((0.0, 2.0), (0.5, 1.0)), // H
+    ((0.5, 2.0), (0.5, 1.0)), // I
+    ((1.0, 2.0), (0.5, 1.0)), // J
+    ((0.5, 1.0), (0.0, 0.0)), // K
+    ((0.5, 1.0), (0.5, 0.0)), // L
+    ((0.5, 1.0), (1.0, 0.0))  // M
+];
+
+fn glyph(c: char) -> u16 {
+    match c.to_ascii_uppercase() {
+        'A' => A1 | A2 | B | C | E | F | G1 | G2,
+        'B' => A1 | A2 | B | C | D1 | D2 | G2 | I | L,
+        'C' => A1 | A2 | D1 | D2 | E | F,
+        'D' => A1 | A2 | B | C | D1 | D2 | I | L,
+        'E' => A1 | A2 | D1 | D2 | E | F | G1,
+        'F' => A1 | A2 | E | F | G1,
+        'G' => A1 | A2 | C | D1 | D2 | E | F | G2,
+        'H' => B | C | E | F | G1 | G2,
+        'I' => A1 | A2 | D1 | D2 | I | L,
+        'J' => B | C | D1 | D2 | E,
+        'K' => E | F | G1 | J | M,
+        'L' => D1 | D2 | E | F,
+        'M' => B | C | E | F | H | J,
+        'N' => B | C | E | F | H | M,
+        'O' => A1 | A2 | B | C | D1 | D2 | E | F,
+        '0' => A1 | A2 | B | C | D1 | D2 | E | F | J | K,
+        'P' => A1 | A2 | B | E | F | G1 | G2,
+        'Q' => A1 | A2 | B | C | D1 | D2 | E | F | M,
+        'R' => A1 | A2 | B | E | F | G1 | G2 | M,
+        'S' | '5' => A1 | A2 | C | D1 | D2 | F | G1 | G2,
+        'T' => A1 | A2 | I | L,
+        'U' => B | C | D1 | D2 | E | F,
+        'V' => E | F | J | K,
+        'W' => B | C | E | F | K | M,
+        'X' => H | J | K | M,
+        'Y' => H | J | L,
+        'Z' => A1 | A2 | D1 | D2 | J | K,
+        '1' => B | C | J,
+        '2' => A1 | A2 | B | D1 | D2 | E | G1 | G2,
+        '3' => A1 | A2 | B | C | D1 | D2 | G2,
+        '4' => B | C | F | G1 | G2,
+        '6' => A1 | A2 | C | D1 | D2 | E | F | G1 | G2,
+        '7' => A1 | A2 | B | C,
+        '8' => A1 | A2 | B | C | D1 | D2 | E | F | G1 | G2,
+        '9' => A1 | A2 | B | C | D1 | D2 | F | G1 | G2,
+        '-' => G1 | G2,
+        '+' => G1 | G2 | I | L,
+        '=' => G1 | G2 | D1 | D2,
+        '/' => J | K,
+        '(' | '<' => J | M,
+        ')' | '>' => H | K,
+        ':' => I | L,
+        '.' | ',' => D1,
+        '_' => D1 | D2,
+        '?' => A1 | A2 | B | G2 | L,
+        _ => 0
+    }
+}
+
@@ -0,0 +1,173 @@
+// Copyright (c) 2017 Ivo Wetzel
+
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+// External Dependencies ------------------------------------------------------
+use gfx;
+use gfx::Factory;
+use gfx::memory::Typed;
+use gfx::traits::FactoryExt;
+use gfx_device_gl;
+use image;
+
+
+// External Dependencies ------------------------------------------------------
+use cgmath;
+use cgmath::{Matrix4, Point3, Vector3, EuclideanSpace};
+use renderer::{ScalingMode, Viewport};
+
+
+// Internal Dependencies ------------------------------------------------------
+use ::error::AssetError;
+use ::core::Course;
+use ::render::MeshView;
+
+
+// Statics --------------------------------------------------------------------
+
+// Empty border around the course as a fraction of the framed extent
+const MARGIN: f32 = 0.05;
+
+// Distance of the near and far plane beyond the course's vertical extent
+const DEPTH_PADDING: f32 = 10.0;
+
+
+// Offscreen Course Thumbnails ------------------------------------------------
+
+// Renders the course top down into an offscreen target, the image can only be
+// read back once the encoder containing the draw has been flushed, i.e. on a
+// later frame
+pub struct Thumbnail {
+    size: (u32, u32),
+    texture: gfx::handle::Texture<gfx_device_gl::Resources, gfx::format::R8_G8_B8_A8>,
+    download: gfx::handle::Buffer<gfx_device_gl::Resources, [u8; 4]>,
+    mesh_view: MeshView,
+    pending: bool
+}
+
+impl Thumbnail {
+
+    pub fn new(factory: &mut gfx_device_gl::Factory, width: u32, height: u32) -> Result<Self, AssetError> {
+
+        let kind = gfx::texture::Kind::D2(width as u16, height as u16, gfx::texture::AaMode::Single);
+        let texture = factory.create_texture::<gfx::format::R8_G8_B8_A8>(
+            kind,
+            1,
+            gfx::RENDER_TARGET | gfx::TRANSFER_SRC,
+            gfx::memory::Usage::Data,
+            Some(gfx::format::ChannelType::Srgb)
+
+        ).map_err(|e| AssetError::Device(e.to_string()))?;
+
+        let color = factory.view_texture_as_render_target(&texture, 0, None).map_err(|e| AssetError::Device(format!("{:?}", e)))?;
+        let (_, _, depth) = factory.create_depth_stencil(width as u16, height as u16).map_err(|e| AssetError::Device(e.to_string()))?;
+        let download = factory.create_download_buffer((width * height) as usize).map_err(|e| AssetError::Device(e.to_string()))?;
+
+        let viewport = Viewport::new(ScalingMode::Stretch, 1.0, width, height);
+        let mut mesh_view = MeshView::new(factory, color, depth, viewport)?;
+        mesh_view.reload(factory, false);
+
+        Ok(Self {
+            size: (width, height),
+            texture: texture,
+            download: download,
+            mesh_view: mesh_view,
+            pending: false
+        })
+
+    }
+
+    // Records the draw and the copy into the download buffer, the background
+    // is left transparent
+    pub fn render(
+        &mut self,
+        encoder: &mut gfx::Encoder<gfx_device_gl::Resources, gfx_device_gl::CommandBuffer>,
+        factory: &mut gfx_device_gl::Factory,
+        course: &mut Course
+    ) {
+
+        let (view, projection) = frame(course.bounds(), self.size.0 as f32 / self.size.1.max(1) as f32);
+
+        self.mesh_view.clear(encoder, [0.0, 0.0, 0.0, 0.0]);
+        self.mesh_view.clear_depth(encoder);
+        for mut m in course.meshes() {
+            self.mesh_view.draw_projected(encoder, factory, projection, view, &mut m);
+        }
+
+        let info = self.texture.get_info().to_raw_image_info(gfx::format::ChannelType::Srgb, 0);
+        match encoder.copy_texture_to_buffer_raw(self.texture.raw(), None, info, self.download.raw(), 0) {
+            Ok(_) => self.pending = true,
+            Err(err) => println!("[Thumbnail] {:?}", err)
+        }
+
+    }
+
+    // None until a recorded render has been flushed
+    pub fn take_image(&mut self, factory: &mut gfx_device_gl::Factory) -> Option<image::RgbaImage> {
+
+        if !self.pending {
+            return None;
+        }
+        self.pending = false;
+
+        let (width, height) = self.size;
+        let reader = match factory.read_mapping(&self.download) {
+            Ok(reader) => reader,
+            Err(err) => {
+                println!("[Thumbnail] {:?}", err);
+                return None;
+            }
+        };
+
+        // GL rows start at the bottom
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for row in reader.chunks(width as usize).rev() {
+            for p in row {
+                pixels.extend_from_slice(p);
+            }
+        }
+        image::RgbaImage::from_raw(width, height, pixels)
+
+    }
+
+}
+
+
+// Helpers --------------------------------------------------------------------
+
+// Top down view and orthographic projection fitting the (min, max) box into
+// the aspect ratio, the longer horizontal side of the course is laid along
+// the longer side of the image so thin courses do not end up as a sliver in
+// the middle
+fn frame(bounds: (Vector3<f32>, Vector3<f32>), aspect: f32) -> (Matrix4<f32>, Matrix4<f32>) {
+
+    let (min, max) = bounds;
+    let center = (min + max) * 0.5;
+    let (dx, dz) = (max.x - min.x, max.z - min.z);
+
+    // Looking down with +X as up puts world Z along the image width
+    let rotated = (dz > dx) == (aspect >= 1.0);
+    let (up, width, height) = if rotated {
+        (Vector3::new(1.0, 0.0, 0.0), dz, dx)
+
+    } else {
+        (Vector3::new(0.0, 0.0, -1.0), dx, dz)
+    };
+
+    let half_width = (width * 0.5).max(height * 0.5 * aspect).max(1.0) * (1.0 + MARGIN);
+    let half_height = half_width / aspect;
+
+    // Loops rise above the rest of the track so the depth range covers the
+    // full vertical extent
+    let eye = Vector3::new(center.x, max.y + DEPTH_PADDING, center.z);
+    let view = Matrix4::look_at(Point3::from_vec(eye), Point3::from_vec(center), up);
+    let projection = cgmath::ortho(-half_width, half_width, -half_height, half_height, 0.0, max.y - min.y + DEPTH_PADDING * 2.0);
+
+    (view, projection)
+
+}
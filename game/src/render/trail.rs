@@ -0,0 +1,201 @@
+// Copyright (c) 2017 Ivo Wetzel
+
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+// External Dependencies ------------------------------------------------------
+use gfx;
+use gfx::Factory;
+use gfx::traits::FactoryExt;
+use gfx_device_gl;
+use std::collections::VecDeque;
+
+use cgmath::{Matrix4, SquareMatrix, Vector3, InnerSpace, Zero};
+use renderer::{ColorBuffer, DepthBuffer, Viewport};
+
+
+// Internal Dependencies ------------------------------------------------------
+use ::error::AssetError;
+use ::core::Camera;
+use ::render::{MeshView, MeshVertex, MeshLocals, MeshData, MeshMeta, RenderStats};
+
+
+// Statics --------------------------------------------------------------------
+const TRAIL_WIDTH: f32 = 2.0;
+const TRAIL_SPEED_WIDTH: f32 = 0.25;
+
+
+// 3D Glider Trail Rendering Implementation -----------------------------------
+pub struct TrailView {
+    points: VecDeque<(Vector3<f32>, Vector3<f32>)>,
+    max_points: usize,
+    color: [f32; 3],
+    vertices: Vec<MeshVertex>,
+    pso: gfx::PipelineState<gfx_device_gl::Resources, MeshMeta>,
+    data: MeshData<gfx_device_gl::Resources>,
+    slice: gfx::Slice<gfx_device_gl::Resources>,
+    stats: RenderStats
+}
+
+impl TrailView {
+
+    pub fn new(
+        factory: &mut gfx_device_gl::Factory,
+        color: ColorBuffer,
+        depth: DepthBuffer,
+        max_points: usize,
+        viewport: Viewport
+
+    ) -> Result<Self, AssetError> {
+
+        // Two triangles per pair of consecutive points
+        let max_points = max_points.max(2);
+        let vertex_count = (max_points - 1) * 6;
+        let vertex_buffer = factory.create_buffer::<MeshVertex>(
+            vertex_count,
+            gfx::buffer::Role::Vertex,
+            gfx::memory::Usage::Dynamic,
+            gfx::Bind::empty()
+
+        ).map_err(|e| AssetError::Device(e.to_string()))?;
+
+        Ok(Self {
+            points: VecDeque::with_capacity(max_points),
+            max_points: max_points,
+            color: [0.0, 0.8, 1.0],
+            vertices: Vec::with_capacity(vertex_count),
+            pso: MeshView::create_pipeline(factory, false)?,
+            data: MeshData {
+                vbuf: vertex_buffer,
+                locals: factory.create_constant_buffer(1),
+                blend_target: color.clone(),
+                blend_ref: [1.0; 4],
+                out_color: color,
+                out_depth: depth,
+                scissor: viewport.rect()
+            },
+            slice: gfx::Slice {
+                instances: None,
+                start: 0,
+                end: 0,
+                buffer: gfx::IndexBuffer::Auto,
+                base_vertex: 0
+            },
+            stats: RenderStats::default()
+        })
+
+    }
+
+    // Adds a new head sample, the oldest sample is dropped once the trail
+    // reaches its maximum length
+    pub fn push(&mut self, position: Vector3<f32>, up: Vector3<f32>) {
+        if self.points.len() == self.max_points {
+            self.points.pop_back();
+        }
+        self.points.push_front((position, up));
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    pub fn stats(&self) -> RenderStats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats.reset();
+    }
+
+    pub fn reload(&mut self, factory: &mut gfx_device_gl::Factory, wireframe: bool) {
+        match MeshView::create_pipeline(factory, wireframe) {
+            Ok(pso) => self.pso = pso,
+            Err(err) => println!("[TrailView] {}", err)
+        }
+    }
+
+    pub fn resize(&mut self, screen: (Viewport, ColorBuffer, DepthBuffer)) {
+        self.data.blend_target = screen.1.clone();
+        self.data.out_color = screen.1;
+        self.data.out_depth = screen.2;
+        self.data.scissor = screen.0.rect();
+    }
+
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.data.scissor = viewport.rect();
+    }
+
+    pub fn draw(
+        &mut self,
+        encoder: &mut gfx::Encoder<gfx_device_gl::Resources, gfx_device_gl::CommandBuffer>,
+        camera: &Camera,
+        view: Matrix4<f32>
+    ) {
+
+        if self.points.len() < 2 {
+            return;
+        }
+
+        // Build a ribbon perpendicular to the up vector and the direction of
+        // travel, the sample spacing grows with speed and widens the ribbon
+        self.vertices.clear();
+        let count = self.points.len();
+        let mut edges = Vec::with_capacity(count);
+        for i in 0..count {
+
+            let (p, up) = self.points[i];
+            let (a, b) = if i + 1 < count {
+                (self.points[i + 1].0, p)
+
+            } else {
+                (p, self.points[i - 1].0)
+            };
+
+            let dir = b - a;
+            let side = up.cross(dir);
+            let side = if side.is_zero() { side } else { side.normalize() };
+            let width = TRAIL_WIDTH + dir.magnitude() * TRAIL_SPEED_WIDTH;
+            let alpha = 1.0 - i as f32 / (count - 1) as f32;
+            edges.push((p + side * width, p - side * width, alpha));
+
+        }
+
+        for e in edges.windows(2) {
+            let (a, b) = (e[0], e[1]);
+            let quad = [(a.0, a.2), (b.0, b.2), (a.1, a.2), (b.0, b.2), (b.1, b.2), (a.1, a.2)];
+            for &(p, alpha) in &quad {
+                self.vertices.push(MeshVertex {
+                    pos: p.into(),
+                    normal: [0.0; 3],
+                    tangent: [0.0; 3],
+                    color: [self.color[0], self.color[1], self.color[2], alpha * 0.75]
+                });
+            }
+        }
+
+        let locals = MeshLocals {
+            model: Matrix4::identity().into(),
+            view: view.into(),
+            proj: camera.projection().into(),
+            wireframe_color: [0.0; 4],
+            ambient: [1.0; 3]
+        };
+
+        self.slice.end = self.vertices.len() as u32;
+        if let Err(err) = encoder.update_buffer(&self.data.vbuf, &self.vertices, 0) {
+            println!("[TrailView] {}", err);
+        }
+        encoder.update_buffer(&self.data.locals, &[locals], 0).unwrap();
+        encoder.draw(&self.slice, &self.pso, &self.data);
+
+        self.stats.draw_calls += 1;
+        self.stats.triangles += self.vertices.len() / 3;
+
+    }
+
+}
+
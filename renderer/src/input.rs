@@ -74,7 +74,26 @@ pub enum Key {
     Return = 40,
     Escape = 41,
     LShift = 42,
-    Unknown = 43
+    F1 = 43,
+    LControl = 44,
+    F2 = 45,
+    F3 = 46,
+    Delete = 47,
+    Plus = 48,
+    Minus = 49,
+    F4 = 50,
+    F5 = 51,
+    F6 = 52,
+    F7 = 53,
+    F8 = 54,
+    F9 = 55,
+    F10 = 56,
+    F11 = 57,
+    F12 = 58,
+    LAlt = 59,
+    PageUp = 60,
+    PageDown = 61,
+    Unknown = 62
 }
 
 impl From<VirtualKeyCode> for Key {
@@ -122,6 +141,25 @@ impl From<VirtualKeyCode> for Key {
             VirtualKeyCode::Return => Key::Return,
             VirtualKeyCode::Escape => Key::Escape,
             VirtualKeyCode::LShift => Key::LShift,
+            VirtualKeyCode::F1 => Key::F1,
+            VirtualKeyCode::LControl => Key::LControl,
+            VirtualKeyCode::LAlt => Key::LAlt,
+            VirtualKeyCode::F2 => Key::F2,
+            VirtualKeyCode::F3 => Key::F3,
+            VirtualKeyCode::F4 => Key::F4,
+            VirtualKeyCode::F5 => Key::F5,
+            VirtualKeyCode::F6 => Key::F6,
+            VirtualKeyCode::F7 => Key::F7,
+            VirtualKeyCode::F8 => Key::F8,
+            VirtualKeyCode::F9 => Key::F9,
+            VirtualKeyCode::F10 => Key::F10,
+            VirtualKeyCode::F11 => Key::F11,
+            VirtualKeyCode::F12 => Key::F12,
+            VirtualKeyCode::Delete => Key::Delete,
+            VirtualKeyCode::PageUp => Key::PageUp,
+            VirtualKeyCode::PageDown => Key::PageDown,
+            VirtualKeyCode::Equals | VirtualKeyCode::Add => Key::Plus,
+            VirtualKeyCode::Minus | VirtualKeyCode::Subtract => Key::Minus,
             _ => Key::Unknown
         }
     }
@@ -329,6 +367,13 @@ impl<I, T, C> InputState<I, T, C> where T: Default + Clone + AdvanceableState, I
         self.fields[index.into()] = to;
     }
 
+    // Used by input playback, unknown indices are ignored
+    pub fn set_index(&mut self, index: usize, to: T) {
+        if let Some(field) = self.fields.get_mut(index) {
+            *field = to;
+        }
+    }
+
     pub fn advance(&mut self) {
         for value in &mut self.fields {
             *value = value.advance();
@@ -348,16 +393,30 @@ impl<I, T, C> InputState<I, T, C> where T: Default + Clone + AdvanceableState, I
 }
 
 pub type Keyboard = InputState<Key, KeyState, ()>;
-pub type Mouse = InputState<Button, ButtonState, (i32, i32)>;
+pub type Mouse = InputState<Button, ButtonState, ((i32, i32), f32)>;
 
 impl Mouse {
 
     pub fn set_position(&mut self, position: (i32, i32)) {
-        self.custom = position;
+        self.custom.0 = position;
     }
 
     pub fn position(&self) -> (i32, i32) {
-        self.custom
+        self.custom.0
+    }
+
+    // Accumulated wheel movement in lines since the start of the frame,
+    // positive values scroll away from the user
+    pub fn wheel(&self) -> f32 {
+        self.custom.1
+    }
+
+    pub fn scroll(&mut self, delta: f32) {
+        self.custom.1 += delta;
+    }
+
+    pub fn clear_wheel(&mut self) {
+        self.custom.1 = 0.0;
     }
 
 }
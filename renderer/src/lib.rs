@@ -14,6 +14,9 @@ extern crate gfx_device_gl;
 extern crate glutin;
 extern crate image;
 
+#[cfg(feature = "gpu_timer")]
+extern crate gfx_gl as gl;
+
 
 
 // STD Dependencies -----------------------------------------------------------
@@ -27,18 +30,30 @@ use glutin::{
     Event as InputEvent,
     EventsLoop,
     ElementState,
+    MouseScrollDelta,
     WindowBuilder, WindowEvent
 };
 
 
 // Internal Dependencies ------------------------------------------------------
 mod input;
+mod record;
 mod texture;
+#[cfg(feature = "gpu_timer")]
+mod timer;
+mod viewport;
 
 use input::{ButtonState, KeyState};
+use record::{InputAction, InputPlayback, InputRecorder};
+#[cfg(feature = "gpu_timer")]
+use timer::GpuTimer;
 
 pub use input::{Key, Keyboard, Button, Mouse};
-pub use texture::Texture;
+pub use record::InputRecording;
+pub use texture::{Texture, TextureHandle};
+#[cfg(feature = "gpu_timer")]
+pub use timer::GPU_TIMER_LAG;
+pub use viewport::{ScalingMode, Viewport};
 
 
 // Type Abstractions ----------------------------------------------------------
@@ -47,6 +62,13 @@ pub type DepthBuffer = gfx::handle::DepthStencilView<gfx_device_gl::Resources, (
 pub type Encoder = gfx::Encoder<gfx_device_gl::Resources, gfx_device_gl::CommandBuffer>;
 pub type Factory = gfx_device_gl::Factory;
 
+
+// Statics --------------------------------------------------------------------
+
+// Seconds of frames the average fps roughly covers
+const FPS_AVERAGE_WINDOW: f32 = 1.0;
+
+
 // Traits ---------------------------------------------------------------------
 pub trait Renderable {
     fn draw(
@@ -56,34 +78,124 @@ pub trait Renderable {
         encoder: &mut Encoder,
         &Keyboard,
         &Mouse,
-        Option<((u32, u32), ColorBuffer, DepthBuffer)>
+        Option<(Viewport, ColorBuffer, DepthBuffer)>
 
     ) where Self: Sized;
+
+    fn frame_stats(&mut self, _: &FrameStats) where Self: Sized {}
+
+    // Handles from Texture::load_async, pending ones are completed before
+    // the next draw
+    fn textures(&mut self) -> Vec<&mut TextureHandle> where Self: Sized {
+        Vec::new()
+    }
+
+    // Called with the average fps once it stayed below the RunConfig low_fps
+    // threshold for the whole duration, and again after every further
+    // duration it does not recover. Never called while input is recorded or
+    // played back.
+    fn low_fps(&mut self, _: f32) where Self: Sized {}
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct FrameStats {
+    // Time spent updating and drawing in seconds
+    pub work_time: f32,
+    // Actual frame time including the wait in seconds, this is always the
+    // raw value even when the dt passed to draw is smoothed
+    pub frame_time: f32,
+    // GPU time in seconds of an earlier frame, results lag GPU_TIMER_LAG
+    // frames behind and are only measured with the gpu_timer feature
+    pub lagging_gpu_time: Option<f32>,
+    // Achieved frames per second averaged over about FPS_AVERAGE_WINDOW,
+    // compare with the RunConfig target fps
+    pub average_fps: f32
+}
+
+impl FrameStats {
+
+    pub fn fps(&self) -> f32 {
+        if self.frame_time > 0.0 {
+            1.0 / self.frame_time
+
+        } else {
+            0.0
+        }
+    }
+
 }
 
 pub struct RenderTarget {
     pub factory: Factory,
     pub width: u32,
     pub height: u32,
+    pub viewport: Viewport,
     pub color: ColorBuffer,
     pub depth: DepthBuffer
 }
 
+#[derive(Debug, Clone)]
+pub struct RunConfig {
+    pub title: String,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    // Remaining frame time below which the limiter spins instead of sleeping
+    pub spin_threshold: Duration,
+    // Replaces the dt passed to draw with an exponential moving average of
+    // the frame times, the value is the weight of the newest frame in 0..1.
+    // This hides small jitter of the limiter but also spreads real hitches
+    // over the following frames so the simulation lags behind the wall clock
+    // for a moment. Unlike a fixed timestep it does not make the simulation
+    // deterministic, it only makes the steps more even.
+    pub dt_smoothing: Option<f32>,
+    // (threshold, duration) for Renderable::low_fps, e.g. to reduce detail
+    // when the average fps cannot keep up with the target
+    pub low_fps: Option<(f32, Duration)>,
+    pub scaling: ScalingMode,
+    pub recording: InputRecording
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self {
+            title: String::new(),
+            width: 800,
+            height: 600,
+            fps: 60,
+            spin_threshold: Duration::from_millis(1),
+            dt_smoothing: None,
+            low_fps: None,
+            scaling: ScalingMode::Letterbox,
+            recording: InputRecording::Off
+        }
+    }
+}
+
 // Public Interface -----------------------------------------------------------
 pub fn run<
     R,
     C: FnOnce(RenderTarget) -> R
 >(
-    title: &str,
-    width: u32,
-    height: u32,
-    fps: u32,
+    config: RunConfig,
     callback: C
 
 ) where R: Renderable {
 
+    let RunConfig {
+        title,
+        width,
+        height,
+        fps,
+        spin_threshold,
+        dt_smoothing,
+        low_fps,
+        scaling,
+        recording
+    } = config;
+
     let builder = WindowBuilder::new()
-        .with_title(title.to_string())
+        .with_title(title)
         .with_dimensions(width, height)
         //.with_multisampling(4);
         //.with_min_dimensions(width, height)
@@ -114,24 +226,65 @@ pub fn run<
 
     > = factory.create_command_buffer().into();
 
+    // Letterboxing keeps the aspect ratio of the initial window size
+    let aspect_ratio = width as f32 / height.max(1) as f32;
+    // The renderable owns the factory, a shared clone uploads textures
+    let mut upload_factory = factory.clone();
     let mut renderable = {
         let refs = RenderTarget {
             factory: factory,
             width: width,
             height: height,
+            viewport: Viewport::new(scaling, aspect_ratio, width, height),
             color: output_color.clone(),
             depth: output_depth.clone()
         };
         callback(refs)
     };
 
+    #[cfg(feature = "gpu_timer")]
+    let mut gpu_timer = GpuTimer::new(&mut device);
+    #[cfg_attr(not(feature = "gpu_timer"), allow(unused_mut))]
+    let mut lagging_gpu_time = None;
+
     let mut mouse_pos = (-1, -1);
-    let mut keyboard = Keyboard::new(48, ());
-    let mut mouse = Mouse::new(2, mouse_pos);
+    let mut keyboard = Keyboard::new(63, ());
+    let mut mouse = Mouse::new(2, (mouse_pos, 0.0));
+
+    let (mut recorder, mut playback) = match recording {
+        InputRecording::Off => (None, None),
+        InputRecording::Record(path) => match InputRecorder::create(&path) {
+            Ok(recorder) => {
+                println!("[Renderer] Recording input to {:?}", path);
+                (Some(recorder), None)
+            },
+            Err(err) => {
+                println!("[Renderer] Failed to create input recording {:?}: {}", path, err);
+                (None, None)
+            }
+        },
+        InputRecording::Playback(path) => match InputPlayback::load(&path) {
+            Ok(playback) => {
+                println!("[Renderer] Playing back input from {:?}", path);
+                (None, Some(playback))
+            },
+            Err(err) => {
+                println!("[Renderer] Failed to load input recording {:?}: {}", path, err);
+                (None, None)
+            }
+        }
+    };
 
     let mut running = true;
+    let mut minimized = false;
+    let mut frame: u64 = 0;
     let mut time: f32 = 0.0;
     let mut dt: f32 = 0.0;
+    let mut average_frame_time: f32 = 0.0;
+    let mut low_fps_time: f32 = 0.0;
+    let low_fps = low_fps.map(|(threshold, duration)| {
+        (threshold, duration.as_secs() as f32 + duration.subsec_nanos() as f32 / 1000000000.0)
+    });
 
     println!("[Renderer] Mainloop started");
     while running {
@@ -140,71 +293,207 @@ pub fn run<
 
         keyboard.advance();
         mouse.advance();
+        mouse.clear_wheel();
 
         let mut resized = None;
+        let mut actions = Vec::new();
         events.poll_events(|event| {
             match event {
                 InputEvent::WindowEvent{ event: WindowEvent::Closed, .. } => {
                     running = false;
                 },
                 InputEvent::WindowEvent{ event: WindowEvent::Focused(_), .. } => {
-                    keyboard.reset();
-                    mouse.reset();
-                    mouse_pos = (-1, -1);
+                    actions.push(InputAction::Reset);
                 },
                 InputEvent::WindowEvent{ event: WindowEvent::MouseMoved(x, y), .. } => {
-                    mouse_pos = (x, y);
-                    mouse.set_position(mouse_pos);
+                    actions.push(InputAction::Move(x, y));
                 },
-                InputEvent::WindowEvent{ event: WindowEvent::MouseInput(ElementState::Pressed, button), .. } => {
-                    if mouse_pos.0 != -1 || mouse_pos.1 != -1 {
-                        mouse.set(button.into(), ButtonState::WasPressed(mouse_pos.0, mouse_pos.1));
-                    }
+                InputEvent::WindowEvent{ event: WindowEvent::MouseWheel(MouseScrollDelta::LineDelta(_, y), _), .. } => {
+                    actions.push(InputAction::Wheel(y));
                 },
-                InputEvent::WindowEvent{ event: WindowEvent::MouseInput(ElementState::Released, button), .. } => {
-                    if mouse_pos.0 != -1 || mouse_pos.1 != -1 {
-                        mouse.set(button.into(), ButtonState::WasReleased(mouse_pos.0, mouse_pos.1));
-                    }
+                InputEvent::WindowEvent{ event: WindowEvent::MouseWheel(MouseScrollDelta::PixelDelta(_, y), _), .. } => {
+                    // Roughly one line per 20 pixels on touchpads
+                    actions.push(InputAction::Wheel(y / 20.0));
                 },
-                InputEvent::WindowEvent{ event: WindowEvent::KeyboardInput(ElementState::Pressed, _, Some(key), _), .. } => {
-                    keyboard.set(key.into(), KeyState::WasPressed);
+                InputEvent::WindowEvent{ event: WindowEvent::MouseInput(state, button), .. } => {
+                    let button: Button = button.into();
+                    actions.push(InputAction::Button(button.into(), state == ElementState::Pressed));
                 },
-                InputEvent::WindowEvent{ event: WindowEvent::KeyboardInput(ElementState::Released, _, Some(key), _), .. } => {
-                    keyboard.set(key.into(), KeyState::WasReleased);
+                InputEvent::WindowEvent{ event: WindowEvent::KeyboardInput(state, _, Some(key), _), .. } => {
+                    let key: Key = key.into();
+                    actions.push(InputAction::Key(key.into(), state == ElementState::Pressed));
+                },
+                // A minimized window reports a zero size, keep the previous
+                // views until it is restored
+                InputEvent::WindowEvent{ event: WindowEvent::Resized(w, h), .. } if w == 0 || h == 0 => {
+                    minimized = true;
                 },
                 InputEvent::WindowEvent{ event: WindowEvent::Resized(w, h), .. } => {
+                    minimized = false;
                     gfx_window_glutin::update_views(
                         &window,
                         &mut output_color,
                         &mut output_depth
                     );
-                    resized = Some(((w, h), output_color.clone(), output_depth.clone()));
+                    let viewport = Viewport::new(scaling, aspect_ratio, w, h);
+                    resized = Some((viewport, output_color.clone(), output_depth.clone()));
                 },
                 _ => {}
             }
         });
 
-        // Draw
-        encoder.clear_depth(&output_depth, 1.0);
-        encoder.clear(&output_color, [0.0, 0.0, 0.0, 1.0]);
-        renderable.draw(time, dt, &mut encoder, &keyboard, &mouse, resized);
-        encoder.flush(&mut device);
-        window.swap_buffers().unwrap();
-        device.cleanup();
+        // Replace live input with the recorded frames, once the recording
+        // ends control is handed back to live input
+        if let Some((recorded_dt, recorded)) = playback.as_mut().and_then(|p| p.next()) {
+            dt = recorded_dt;
+            actions = recorded;
+
+        } else if playback.is_some() {
+            println!("[Renderer] Input playback finished after {} frames", frame);
+            playback = None;
+        }
+
+        for action in &actions {
+            match *action {
+                InputAction::Reset => {
+                    keyboard.reset();
+                    mouse.reset();
+                    mouse_pos = (-1, -1);
+                },
+                InputAction::Move(x, y) => {
+                    mouse_pos = (x, y);
+                    mouse.set_position(mouse_pos);
+                },
+                InputAction::Wheel(delta) => {
+                    mouse.scroll(delta);
+                },
+                InputAction::Button(button, pressed) => {
+                    if mouse_pos.0 != -1 || mouse_pos.1 != -1 {
+                        mouse.set_index(button, if pressed {
+                            ButtonState::WasPressed(mouse_pos.0, mouse_pos.1)
+
+                        } else {
+                            ButtonState::WasReleased(mouse_pos.0, mouse_pos.1)
+                        });
+                    }
+                },
+                InputAction::Key(key, pressed) => {
+                    keyboard.set_index(key, if pressed {
+                        KeyState::WasPressed
+
+                    } else {
+                        KeyState::WasReleased
+                    });
+                }
+            }
+        }
+
+        if let Some(ref mut r) = recorder {
+            if let Err(err) = r.frame(frame, dt, &actions[..]) {
+                println!("[Renderer] Input recording stopped: {}", err);
+                recorder = None;
+            }
+        }
+        frame += 1;
+
+        // Upload textures that finished decoding in the background
+        for handle in renderable.textures() {
+            if handle.complete(&mut upload_factory) {
+                if let Some(err) = handle.error() {
+                    println!("[Renderer] Failed to load texture {:?}: {}", handle.path(), err);
+                }
+            }
+        }
+
+        // Draw, nothing is visible while minimized so the game is paused
+        // until the window is restored
+        if !minimized {
+            encoder.clear_depth(&output_depth, 1.0);
+            encoder.clear(&output_color, [0.0, 0.0, 0.0, 1.0]);
+            renderable.draw(time, dt, &mut encoder, &keyboard, &mouse, resized);
+            #[cfg(feature = "gpu_timer")]
+            gpu_timer.begin(&mut device);
+
+            encoder.flush(&mut device);
+
+            #[cfg(feature = "gpu_timer")]
+            {
+                lagging_gpu_time = gpu_timer.end(&mut device).or(lagging_gpu_time);
+            }
+
+            window.swap_buffers().unwrap();
+            device.cleanup();
+        }
 
         // Limit FPS
-        let remaining = started.elapsed();
-        if remaining < frame_time {
-            thread::sleep(frame_time - remaining);
+        let work = started.elapsed();
+        if work < frame_time {
+
+            // Sleep for most of the remaining time and spin for the rest
+            // since sleep granularity is too coarse on some platforms
+            let remaining = frame_time - work;
+            if remaining > spin_threshold {
+                thread::sleep(remaining - spin_threshold);
+            }
+
+            while started.elapsed() < frame_time {
+                thread::yield_now();
+            }
 
         } else {
             println!("Exceeded frame time: {:?}", started.elapsed());
         }
 
         let e = started.elapsed();
-        dt = e.as_secs() as f32 + e.subsec_nanos() as f32 / 1000000000.0;
+        let raw_dt = e.as_secs() as f32 + e.subsec_nanos() as f32 / 1000000000.0;
+        dt = match dt_smoothing {
+            Some(weight) if dt > 0.0 => {
+                let weight = weight.max(0.0).min(1.0);
+                dt * (1.0 - weight) + raw_dt * weight
+            },
+            _ => raw_dt
+        };
         time += dt;
 
+        // Weighted by frame time so the window covers the same duration at
+        // any frame rate
+        average_frame_time = if average_frame_time > 0.0 {
+            let weight = (raw_dt / FPS_AVERAGE_WINDOW).min(1.0);
+            average_frame_time * (1.0 - weight) + raw_dt * weight
+
+        } else {
+            raw_dt
+        };
+
+        let average_fps = if average_frame_time > 0.0 {
+            1.0 / average_frame_time
+
+        } else {
+            0.0
+        };
+
+        renderable.frame_stats(&FrameStats {
+            work_time: work.as_secs() as f32 + work.subsec_nanos() as f32 / 1000000000.0,
+            frame_time: raw_dt,
+            lagging_gpu_time: lagging_gpu_time,
+            average_fps: average_fps
+        });
+
+        // Minimized windows skip drawing and say nothing about the detail,
+        // recorded input only replays correctly without adaptive changes
+        if let Some((threshold, duration)) = low_fps {
+            if average_fps < threshold && !minimized && recorder.is_none() && playback.is_none() {
+                low_fps_time += raw_dt;
+                if low_fps_time >= duration {
+                    low_fps_time = 0.0;
+                    renderable.low_fps(average_fps);
+                }
+
+            } else {
+                low_fps_time = 0.0;
+            }
+        }
+
     }
 
     println!("[Renderer] Mainloop ended");
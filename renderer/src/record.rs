@@ -0,0 +1,128 @@
+// Copyright (c) 2017 Ivo Wetzel
+
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+// STD Dependencies -----------------------------------------------------------
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+
+
+// Input Recording ------------------------------------------------------------
+#[derive(Debug, Clone)]
+pub enum InputRecording {
+    Off,
+    Record(PathBuf),
+    Playback(PathBuf)
+}
+
+// Input changes as applied to the keyboard and mouse state
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputAction {
+    Key(usize, bool),
+    Button(usize, bool),
+    Move(i32, i32),
+    Wheel(f32),
+    Reset
+}
+
+// Writes one line per frame with its dt followed by one line per action
+//
+//   F <frame> <dt>
+//   K <key> <pressed> | B <button> <pressed> | M <x> <y> | W <delta> | R
+pub struct InputRecorder {
+    writer: BufWriter<File>
+}
+
+impl InputRecorder {
+
+    pub fn create(path: &PathBuf) -> io::Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?)
+        })
+    }
+
+    pub fn frame(&mut self, frame: u64, dt: f32, actions: &[InputAction]) -> io::Result<()> {
+
+        writeln!(self.writer, "F {} {}", frame, dt)?;
+        for action in actions {
+            match *action {
+                InputAction::Key(key, pressed) => writeln!(self.writer, "K {} {}", key, pressed as u8)?,
+                InputAction::Button(button, pressed) => writeln!(self.writer, "B {} {}", button, pressed as u8)?,
+                InputAction::Move(x, y) => writeln!(self.writer, "M {} {}", x, y)?,
+                InputAction::Wheel(delta) => writeln!(self.writer, "W {}", delta)?,
+                InputAction::Reset => writeln!(self.writer, "R")?
+            }
+        }
+
+        // Flush every frame so a crash still leaves a usable recording
+        self.writer.flush()
+
+    }
+
+}
+
+pub struct InputPlayback {
+    frames: VecDeque<(f32, Vec<InputAction>)>
+}
+
+impl InputPlayback {
+
+    pub fn load(path: &PathBuf) -> io::Result<Self> {
+
+        let mut frames: VecDeque<(f32, Vec<InputAction>)> = VecDeque::new();
+        for (i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+
+            let line = line?;
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let invalid = || io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid input recording line {}: {}", i + 1, line)
+            );
+
+            let int = |index: usize| -> io::Result<i64> {
+                parts.get(index).and_then(|p| p.parse().ok()).ok_or_else(&invalid)
+            };
+
+            let action = match parts.get(0) {
+                Some(&"F") => {
+                    let dt = parts.get(2).and_then(|p| p.parse().ok()).ok_or_else(&invalid)?;
+                    frames.push_back((dt, Vec::new()));
+                    continue;
+                },
+                Some(&"K") => InputAction::Key(int(1)? as usize, int(2)? != 0),
+                Some(&"B") => InputAction::Button(int(1)? as usize, int(2)? != 0),
+                Some(&"M") => InputAction::Move(int(1)? as i32, int(2)? as i32),
+                Some(&"W") => InputAction::Wheel(parts.get(1).and_then(|p| p.parse().ok()).ok_or_else(&invalid)?),
+                Some(&"R") => InputAction::Reset,
+                None => continue,
+                _ => return Err(invalid())
+            };
+
+            match frames.back_mut() {
+                Some(frame) => frame.1.push(action),
+                None => return Err(invalid())
+            }
+
+        }
+
+        Ok(Self {
+            frames: frames
+        })
+
+    }
+
+    // Returns the dt and actions of the next recorded frame, None once the
+    // recording is exhausted
+    pub fn next(&mut self) -> Option<(f32, Vec<InputAction>)> {
+        self.frames.pop_front()
+    }
+
+}
+
@@ -8,8 +8,10 @@
 
 
 // STD Dependencies -----------------------------------------------------------
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::error::Error;
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::thread;
 
 
 // External Dependencies ------------------------------------------------------
@@ -19,26 +21,85 @@ use gfx_device_gl;
 use image;
 
 
+// Statics --------------------------------------------------------------------
+const DEFAULT_ANISOTROPY: u8 = 8;
+
+
 // Texture Abstraction --------------------------------------------------------
 #[derive(Debug)]
 pub struct Texture {
     view: gfx::handle::ShaderResourceView<gfx_device_gl::Resources, [f32; 4]>,
+    sampler: gfx::handle::Sampler<gfx_device_gl::Resources>,
     size: (u32, u32)
 }
 
 impl Texture {
 
-    pub fn new(factory: &mut gfx_device_gl::Factory, path: &Path) -> Result<Self, Box<Error>> {
-
+    pub fn new(factory: &mut gfx_device_gl::Factory, path: &Path) -> Result<Self, Box<dyn Error>> {
         let img = image::open(path)?.to_rgba();
+        Texture::from_image(factory, img)
+    }
+
+    // Decodes the image on a background thread, the renderer uploads it on
+    // the main thread once it is ready since the factory is bound to the GL
+    // context. The handle has to be returned from Renderable::textures until
+    // it is no longer pending.
+    pub fn load_async(path: &Path) -> TextureHandle {
+
+        let (tx, rx) = channel();
+        let thread_path = path.to_path_buf();
+        thread::spawn(move || {
+            tx.send(image::open(&thread_path).map(|img| img.to_rgba())).ok();
+        });
+
+        TextureHandle {
+            path: path.to_path_buf(),
+            state: TextureState::Decoding(rx)
+        }
+
+    }
+
+    fn from_image(factory: &mut gfx_device_gl::Factory, img: image::RgbaImage) -> Result<Self, Box<dyn Error>> {
+
         let (width, height) = img.dimensions();
         let kind = gfx::texture::Kind::D2(width as u16, height as u16, gfx::texture::AaMode::Single);
-        let (_, view) = factory.create_texture_immutable_u8::<gfx::format::Srgba8>(kind, &[&img])?;
+
+        // Build the full mip chain on upload so distant and grazing parts of
+        // the track do not shimmer
+        let mut levels = vec![img];
+        loop {
+            let (w, h) = levels[levels.len() - 1].dimensions();
+            if w == 1 && h == 1 {
+                break;
+            }
+            let next = image::imageops::resize(
+                &levels[levels.len() - 1],
+                (w / 2).max(1),
+                (h / 2).max(1),
+                image::FilterType::Triangle
+            );
+            levels.push(next);
+        }
+
+        let data: Vec<&[u8]> = levels.iter().map(|l| &**l).collect();
+        let (_, view) = factory.create_texture_immutable_u8::<gfx::format::Srgba8>(kind, &data[..])?;
 
         Ok(Self {
             view: view,
+            sampler: create_sampler(factory, DEFAULT_ANISOTROPY),
             size: (width, height)
         })
+
+    }
+
+    // Recreates the sampler with the given anisotropy, 1 disables it and
+    // falls back to plain trilinear filtering
+    pub fn set_anisotropy(&mut self, factory: &mut gfx_device_gl::Factory, level: u8) {
+        self.sampler = create_sampler(factory, level);
+    }
+
+    pub fn sampler(&self) -> gfx::handle::Sampler<gfx_device_gl::Resources> {
+        self.sampler.clone()
     }
 
     pub fn bind(&self) -> gfx::handle::ShaderResourceView<gfx_device_gl::Resources, [f32; 4]> {
@@ -51,3 +112,86 @@ impl Texture {
 
 }
 
+
+// Helpers --------------------------------------------------------------------
+fn create_sampler(factory: &mut gfx_device_gl::Factory, anisotropy: u8) -> gfx::handle::Sampler<gfx_device_gl::Resources> {
+
+    let filter = match anisotropy.max(1).min(16) {
+        1 => gfx::texture::FilterMethod::Trilinear,
+        level => gfx::texture::FilterMethod::Anisotropic(level)
+    };
+
+    factory.create_sampler(gfx::texture::SamplerInfo::new(filter, gfx::texture::WrapMode::Tile))
+
+}
+
+
+// Asynchronous Texture Loading -----------------------------------------------
+enum TextureState {
+    Decoding(Receiver<Result<image::RgbaImage, image::ImageError>>),
+    Loaded(Texture),
+    Failed(Box<dyn Error>)
+}
+
+pub struct TextureHandle {
+    path: PathBuf,
+    state: TextureState
+}
+
+impl TextureHandle {
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_pending(&self) -> bool {
+        if let TextureState::Decoding(_) = self.state {
+            true
+
+        } else {
+            false
+        }
+    }
+
+    pub fn texture(&self) -> Option<&Texture> {
+        if let TextureState::Loaded(ref texture) = self.state {
+            Some(texture)
+
+        } else {
+            None
+        }
+    }
+
+    pub fn error(&self) -> Option<&dyn Error> {
+        if let TextureState::Failed(ref err) = self.state {
+            Some(&**err)
+
+        } else {
+            None
+        }
+    }
+
+    // Uploads the decoded image, returns whether the handle stopped pending
+    // with this call
+    pub fn complete(&mut self, factory: &mut gfx_device_gl::Factory) -> bool {
+
+        let result = match self.state {
+            TextureState::Decoding(ref rx) => match rx.try_recv() {
+                Ok(result) => result,
+                Err(TryRecvError::Empty) => return false,
+                Err(TryRecvError::Disconnected) => {
+                    Err(image::ImageError::FormatError("Texture loader thread exited unexpectedly".to_string()))
+                }
+            },
+            _ => return false
+        };
+
+        self.state = match result.map_err(|e| e.into()).and_then(|img| Texture::from_image(factory, img)) {
+            Ok(texture) => TextureState::Loaded(texture),
+            Err(err) => TextureState::Failed(err)
+        };
+        true
+
+    }
+
+}
@@ -0,0 +1,79 @@
+// Copyright (c) 2017 Ivo Wetzel
+
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+// External Dependencies ------------------------------------------------------
+use gfx_device_gl::Device;
+use gl;
+
+
+// Statics --------------------------------------------------------------------
+
+// Number of frames a query result is read back after it was issued, this
+// keeps the CPU from waiting on the GPU to finish the frame
+pub const GPU_TIMER_LAG: usize = 2;
+const QUERIES: usize = GPU_TIMER_LAG + 1;
+
+
+// GPU Frame Timer ------------------------------------------------------------
+pub struct GpuTimer {
+    queries: [u32; QUERIES],
+    issued: [bool; QUERIES],
+    index: usize
+}
+
+impl GpuTimer {
+
+    pub fn new(device: &mut Device) -> Self {
+        let mut queries = [0; QUERIES];
+        unsafe {
+            device.with_gl(|gl| gl.GenQueries(QUERIES as i32, queries.as_mut_ptr()));
+        }
+        Self {
+            queries: queries,
+            issued: [false; QUERIES],
+            index: 0
+        }
+    }
+
+    pub fn begin(&mut self, device: &mut Device) {
+        let query = self.queries[self.index];
+        unsafe {
+            device.with_gl(|gl| gl.BeginQuery(gl::TIME_ELAPSED, query));
+        }
+    }
+
+    // Returns the GPU time in seconds of the frame issued GPU_TIMER_LAG
+    // frames ago, None while that result is not available yet
+    pub fn end(&mut self, device: &mut Device) -> Option<f32> {
+
+        self.issued[self.index] = true;
+        self.index = (self.index + 1) % QUERIES;
+
+        let query = self.queries[self.index];
+        let issued = self.issued[self.index];
+        let mut elapsed = None;
+        unsafe {
+            device.with_gl(|gl| {
+                gl.EndQuery(gl::TIME_ELAPSED);
+                if issued {
+                    let mut available = 0;
+                    gl.GetQueryObjectiv(query, gl::QUERY_RESULT_AVAILABLE, &mut available);
+                    if available != 0 {
+                        let mut nanos = 0;
+                        gl.GetQueryObjectui64v(query, gl::QUERY_RESULT, &mut nanos);
+                        elapsed = Some(nanos as f32 / 1000000000.0);
+                    }
+                }
+            });
+        }
+        elapsed
+
+    }
+
+}
@@ -0,0 +1,111 @@
+// Copyright (c) 2017 Ivo Wetzel
+
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+
+// External Dependencies ------------------------------------------------------
+use gfx;
+
+
+// Viewport Scaling -----------------------------------------------------------
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ScalingMode {
+    // Fill the whole window, the aspect ratio follows the window
+    Stretch,
+    // Keep the initial aspect ratio and pillar / letterbox the remainder
+    Letterbox
+}
+
+// Visible area of the window in pixels, x and y are measured from the bottom
+// left corner like GL does
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Viewport {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub window: (u32, u32)
+}
+
+impl Viewport {
+
+    pub fn new(mode: ScalingMode, aspect_ratio: f32, width: u32, height: u32) -> Self {
+
+        let (w, h) = match mode {
+            ScalingMode::Stretch => (width, height),
+            ScalingMode::Letterbox => {
+                // A minimized window reports a zero size, fall back to an
+                // empty viewport instead of dividing by zero
+                if width == 0 || height == 0 {
+                    (0, 0)
+
+                } else if width as f32 / height as f32 > aspect_ratio {
+                    (((height as f32 * aspect_ratio).round() as u32).min(width), height)
+
+                } else {
+                    (width, ((width as f32 / aspect_ratio).round() as u32).min(height))
+                }
+            }
+        };
+
+        Self {
+            x: (width - w) / 2,
+            y: (height - h) / 2,
+            width: w,
+            height: h,
+            window: (width, height)
+        }
+
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    // Scale from full window clip space into the viewport, applied on top of
+    // the projection since gfx always sets the viewport to the whole target
+    pub fn scale(&self) -> (f32, f32) {
+        (
+            self.width.max(1) as f32 / self.window.0.max(1) as f32,
+            self.height.max(1) as f32 / self.window.1.max(1) as f32
+        )
+    }
+
+    // Clip space center of the viewport within the window, translating by it
+    // after the scale moves the projection into the viewport
+    pub fn offset(&self) -> (f32, f32) {
+        (
+            (self.x as f32 + self.width as f32 * 0.5) / self.window.0.max(1) as f32 * 2.0 - 1.0,
+            (self.y as f32 + self.height as f32 * 0.5) / self.window.1.max(1) as f32 * 2.0 - 1.0
+        )
+    }
+
+    // Left and right halves for side by side rendering
+    pub fn split(&self) -> (Self, Self) {
+        let half = self.width / 2;
+        (
+            Self { width: half, .. *self },
+            Self { x: self.x + half, width: self.width - half, .. *self }
+        )
+    }
+
+    pub fn rect(&self) -> gfx::Rect {
+        gfx::Rect {
+            x: self.x as u16,
+            y: self.y as u16,
+            w: self.width as u16,
+            h: self.height as u16
+        }
+    }
+
+    // Viewport covering the whole window, for screen space overlays
+    pub fn full(&self) -> Self {
+        Viewport::new(ScalingMode::Stretch, 1.0, self.window.0, self.window.1)
+    }
+
+}
+